@@ -1,299 +1,2554 @@
+use brainfuck::{emit_code, format_code, generate_code, lint_code, optimise_code, parse_code, sanitise_code, split_bang_input, translate_mapping, translate_ook, BrainfuckError, CellSize, EmitTarget, EofMode, Interpreter, Op, Options, Position, State, INSTRUCTIONS};
 use console::Term;
 use std::env;
 use std::fs;
-use std::process::exit;
-use regex::Regex;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::process::{exit, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const RED:   &str = "\u{001b}[31m";
 const GREEN: &str = "\u{001b}[32m";
 const CYAN:  &str = "\u{001b}[38;2;145;231;255m";
 const WHITE: &str = "\u{001b}[37m";
 
-fn throw_exception(error_name: &str, error_message: &str) -> () {
-    println!("{}{}: {}{}", RED, error_name, error_message, WHITE);
+static COLOURS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// Whether errors are printed as JSON for other programs to read, with
+// --error-format json, instead of as a line of text.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+// Set when Ctrl-C is pressed while the code is running, so it can be
+// stopped between two steps instead of the whole program being killed.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// When several files are run as one, the name of each with the line it starts
+// on in the joined code, so positions can be shown in the file they're from.
+static SOURCE_FILES: OnceLock<Vec<(String, usize)>> = OnceLock::new();
+
+// Ctrl-C is caught with the C library's signal(), which only Unix has,
+// so everything to do with it is only built there.
+#[cfg(unix)]
+extern "C" {
+    fn signal(signal_number: i32, handler: extern "C" fn(i32)) -> usize;
+}
+
+// SIGINT is 2 on every Unix.
+#[cfg(unix)]
+const SIGINT: i32 = 2;
+
+#[cfg(unix)]
+extern "C" fn handle_interrupt(_: i32) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+// Catch Ctrl-C from here on, rather than letting it kill the program. This
+// is only done while running code without the debugger, which waits on the
+// keyboard and still needs Ctrl-C to get out of it.
+#[cfg(unix)]
+fn catch_interrupts() {
+    unsafe {
+        signal(SIGINT, handle_interrupt);
+    }
+}
+
+// Everywhere else, Ctrl-C isn't caught and kills the program straight away
+// like it did before, without showing what's in the memory.
+#[cfg(not(unix))]
+fn catch_interrupts() {}
+
+// All colours go through here, so they can be turned on or off in one
+// place with --color or --no-color, the NO_COLOR environment variable,
+// or when the output is not a terminal that supports them.
+fn colour(code: &'static str) -> &'static str {
+    if COLOURS_ENABLED.load(Ordering::Relaxed) {
+        code
+    }
+    else {
+        ""
+    }
+}
+
+// Errors exit with a nonzero code so scripts can tell the interpreter
+// failed. Only a normal run or displaying the help exits with 0.
+fn throw_exception(error_name: &str, error_message: &str) -> ! {
+    print_exception(error_name, error_message);
+    exit(1)
+}
+
+fn print_exception(error_name: &str, error_message: &str) {
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        eprintln!("{{\"error\": {}, \"message\": {}}}", json_string(error_name), json_string(error_message));
+    }
+    else {
+        eprintln!("{}{}: {}{}", colour(RED), error_name, error_message, colour(WHITE));
+    }
+}
+
+// Writes the text as a JSON string, leaving out any colours in it.
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Colours are escape codes ending in "m".
+            '\u{001b}' => {
+                for code_char in chars.by_ref() {
+                    if code_char == 'm' {
+                        break;
+                    }
+                }
+            }
+
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c)
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+fn display_help() {
+    println!("
+Brainfuck Interpreter
+---------------------
+
+This is an executable that can run brainfuck files, created by axololly on GitHub.
+        
+To use this, navigate to the directory with the brainfuck file (marked with the .bf
+extension) and run a command that looks like this in terminal:
+
+    {0}brainfuck your-file.bf{1}
+
+Several files can be given too, which are joined together in order and run as one:
+
+    {0}brainfuck lib.bf main.bf{1}
+
+Alternatively, code can be run straight from the terminal without a file:
+
+    {0}brainfuck -c \"+++++[>+<-]\"{1}
+
+Or piped in from another program, using {0}-{1} in place of the file:
+
+    {0}cat your-file.bf | brainfuck -{1}
+
+
+Flags
+-----
+
+    {0}--allow-debug-char{1}  treat {0}#{1} as an instruction that shows the memory blocks
+                        around the pointer when it is reached.
+
+    {0}--bang-input{1}        run the code before the first {0}!{1} in it, with everything after it
+                        as the input for the {0},{1} instruction. Any other {0}!{1} in the
+                        input has to be written as {0}\\!{1}.
+
+    {0}--bench{1} [runs]      run the code this many times without showing its output,
+                        then show how long it took. (default: {0}10{1})
+
+    {0}--bidirectional{1}     let the pointer move left of the memory block it starts on,
+                        into memory blocks that are numbered from {0}-1{1} down, with as
+                        many of them as there are from {0}0{1} up.
+
+    {0}--break{1} <positions> pause at each of the given positions in the sanitised code
+                        (separated by commas, eg. {0}4,12{1}) to show the memory.
+
+    {0}-c{1}, {0}--code{1} <code>   run the given code instead of a file.
+
+    {0}--cell-display{1} <mode>
+                        how memory blocks are shown by {0}--debug{1} and the debugger:
+                        {0}char{1} as characters (with a {0}.{1} for ones that can't be printed),
+                        {0}dec{1} as numbers, or {0}hex{1} as hexadecimal. (default: {0}dec{1})
+
+    {0}--cell-size{1} <bits>  how many bits each memory block has: {0}8{1}, {0}16{1} or {0}32{1}. (default: {0}8{1})
+
+    {0}--color{1} <when>      when to print in colour: {0}auto{1} when the output is a terminal
+                        that supports it and {0}NO_COLOR{1} isn't set, {0}always{1}, or {0}never{1}.
+                        (default: {0}auto{1})
+
+    {0}--compat{1} <preset>   set the options other interpreters run code with, all at once.
+                        Any of the options given as well are used instead:
+
+                          {0}classic{1}    {0}--cell-size 8 --wrap both --eof unchanged --memory 30000{1}
+                          {0}beef{1}       {0}--cell-size 8 --wrap both --eof zero --memory 30000{1}
+                          {0}bfdev{1}      {0}--cell-size 32 --wrap both --eof max --memory 65536{1}
+
+    {0}--count-only{1}        run the code without any input or output, as if {0},{1} always
+                        reached the end of the input, then show how many steps it
+                        took and how long.
+
+    {0}-d{1}, {0}--debug{1}         show the contents of any non-zero memory blocks after execution,
+                        or once it is stopped with {0}Ctrl-C{1}.
+
+    {0}--dialect{1} <dialect> the language the code is written in: {0}brainfuck{1} or {0}ook{1}.
+                        (default: {0}brainfuck{1})
+
+    {0}--dump-ir{1}           print the ops the code is turned into, one on each line, instead
+                        of running it. Runs of instructions that are collapsed and
+                        loops that clear a memory block show up as a single op.
+
+    {0}--dump-memory{1} <file>
+                        write the pointer and the non-zero memory blocks to a file
+                        as JSON once the code has run.
+
+    {0}--emit{1} <language>   print the code translated into another language instead of
+                        running it: {0}c{1}, {0}rust{1}, or {0}wat{1} for a WebAssembly text module.
+
+    {0}--encoding{1} <encoding>
+                        how the bytes written by {0}.{1} are turned into text:
+
+                          {0}ascii{1}      write bytes above {0}127{1} as {0}?{1}.
+                          {0}latin1{1}     write every byte exactly as it is. (default)
+                          {0}utf8{1}       put the bytes of each character back together,
+                                     writing the replacement character for any bytes
+                                     that aren't UTF-8.
+
+                        Input isn't changed by this. {0},{1} always reads a single byte, so
+                        a character that's more than one byte in UTF-8 takes a {0},{1} for
+                        each of them, and {0},[.,]{1} copies any input as it is.
+
+    {0}--eof{1} <mode>        what the {0},{1} instruction does once there is no more input:
+
+                          {0}unchanged{1}  leave the memory block as it is.
+                          {0}zero{1}       set the memory block to {0}0{1}. (default)
+                          {0}max{1}        set the memory block to its largest value, eg. {0}255{1}.
+
+    {0}--error-format{1} <format>
+                        how errors are printed: {0}human{1} as a line of text, or {0}json{1} as
+                        an object with the error, its position, line and column, and
+                        the message, eg. {0}{{\"error\": \"SyntaxError\", \"position\": 42, ...}}{1}
+                        (default: {0}human{1})
+
+    {0}--format{1}            print the code laid out neatly instead of running it, with
+                        while loops on their own lines and indented by one space.
+
+    {0}--format-width{1} <instructions>
+                        the most instructions on a line with {0}--format{1}, which it
+                        also turns on. (default: {0}40{1})
+
+    {0}--frame-rate{1} <steps>
+                        how many steps {0}--visualize{1} shows a second, which it also
+                        turns on. (default: {0}20{1})
+
+    {0}--generate{1} <text>   print brainfuck code that prints the given text when it's run,
+                        instead of running any code.
+
+    {0}--heatmap{1}           show how many times each memory block was read or written
+                        in the memory breakdown, coloured by how often. This also
+                        turns on {0}--debug{1}.
+
+    {0}--hello{1}             run a built-in program that prints {0}Hello World!{1}, to check
+                        everything works, and exit with an error if it doesn't.
+
+    {0}-h{1}, {0}--help{1}          show this message.
+
+    {0}--input{1} <file>      read input for the {0},{1} instruction from a file instead of
+                        the keyboard.
+
+    {0}--lint{1}              warn about code that is most likely a mistake, like empty
+                        while loops, before running it.
+
+    {0}--load-state{1} <file> carry on running from a state saved with {0}--save-state{1}.
+
+    {0}--map{1} <characters>  run code that uses different characters for the instructions,
+                        given in the order {0}+-<>[].,{1}, eg. {0}--map \"ab<>[].,\"{1}. Any
+                        of {0}+-<>[].,{1} that aren't in the mapping are ignored.
+
+    {0}--max-output{1} <bytes>
+                        stop with an {2}OutputLimitError{1} once {0}.{1} has output this many bytes.
+
+    {0}--max-steps{1} <steps> stop with a {2}StepLimitError{1} after running this many instructions.
+
+    {0}--memory{1} <size>     the number of memory blocks available, which are only allocated
+                        as the pointer reaches them. Going past the last one throws
+                        an {2}OutOfBoundsError{1} with this size. The memory starts small
+                        and only grows up to this, so it's also the most memory a run
+                        can use, and there's no separate maximum. (default: {0}30000{1})
+
+    {0}--memory-window{1} <blocks>
+                        with {0}--debug{1}, show this many memory blocks on either side of
+                        the pointer (including empty ones) instead of every non-zero
+                        memory block.
+
+    {0}--minify{1}            print the code without any comments or whitespace instead
+                        of running it.
+
+    {0}--no-color{1}          print everything without colours, the same as {0}--color never{1}.
+                        This also happens when the {0}NO_COLOR{1} environment variable
+                        is set, or the output is not a terminal.
+
+    {0}--numeric{1}           make {0}.{1} print memory blocks as numbers instead of characters.
+
+    {0}-o{1}, {0}--output{1} <file> write the output of {0}.{1} to a file instead of the terminal.
+
+    {0}--output-newline{1} <mode>
+                        how newlines written by {0}.{1} are changed on the way out:
+
+                          {0}lf{1}         write them exactly as they are. (default)
+                          {0}crlf{1}       write each {0}\\n{1} as {0}\\r\\n{1}.
+                          {0}strip-cr{1}   leave out every {0}\\r{1}.
+
+    {0}--playlist{1} <directory>
+                        run every {0}.bf{1} file in the directory instead, in order of
+                        their names, waiting for a keypress between each one.
+
+    {0}--profile{1}           show how many times each instruction ran, how long the
+                        code took to run, and which while loops ran the most.
+
+    {0}-q{1}, {0}--quiet{1}         only print the output of the code, without the blank lines
+                        around it or the message when there is no output.
+
+    {0}--random-input{1}      make {0},{1} read random bytes instead, which never run out. They
+                        come from a SplitMix64 generator, using the seed given with
+                        {0}--seed{1}, or a new one that gets shown if there isn't one.
+
+    {0}--repl{1}              read and run code one line at a time, keeping the memory
+                        between lines. With {0}--debug{1}, the current memory block is
+                        shown after each line. Press {0}Ctrl-D{1} to exit.
+
+    {0}--reverse-debug{1}     the same as {0}--step{1}, but every instruction can also be
+                        undone by typing {0}b{1}, to go back through the code.
+
+    {0}--save-state{1} <file> save the memory, pointer, and where the code and input got up
+                        to once the code stops (including when it is quit from
+                        the debugger or reaches the step limit).
+
+    {0}--seed{1} <number>     the seed for the random bytes read with {0}--random-input{1}, so
+                        the same seed always gives the same input.
+
+    {0}--show-zeros{1}        show the memory blocks that are {0}0{1} in the memory breakdown
+                        as well, up to the furthest the pointer went.
+
+    {0}--signed-cells{1}      treat memory blocks as signed (eg. {0}-128{1} to {0}127{1}), where they
+                        always wrap around: {0}+{1} on {0}127{1} gives {0}-128{1} and {0}-{1} on {0}-128{1}
+                        gives {0}127{1}. The {0}.{1} instruction outputs the same byte as the
+                        unsigned value would (eg. {0}255{1} for {0}-1{1}).
+
+    {0}--stats{1}             show how many instructions ran, how many bytes were output
+                        and input, and the furthest the pointer went.
+
+    {0}--stdin{1} <text>      read input for the {0},{1} instruction from the given text
+                        instead of the keyboard.
+
+    {0}--step{1}              pause before each instruction to show the pointer and the
+                        memory blocks around it, waiting for a command to go on.
+
+    {0}--strict{1}            throw a {2}SyntaxError{1} for any character that isn't an instruction,
+                        instead of ignoring it as part of a comment.
+
+    {0}--tape-offset{1} <block>
+                        start the pointer on this memory block instead of the first
+                        one, leaving the ones before it free. (default: {0}0{1})
+
+    {0}--test{1} <directory>  run every {0}.bf{1} file in the directory instead, and check its
+                        output is the same as the {0}.out{1} file with the same name. Input
+                        is read from the {0}.in{1} file with the same name, if there is one.
+
+    {0}--trace{1} <file>      write a line to a file for every instruction that runs, with
+                        the step, the instruction, and the pointer and memory block
+                        before and after it.
+
+    {0}--unsafe-no-checks{1}  run faster by not checking that {0}+{1}, {0}-{1}, {0}>{1} and {0}<{1} stay in range.
+                        Memory blocks wrap around their range and the pointer wraps
+                        around the memory instead of erroring, so only use this for
+                        code that is known to work, as anything else can misbehave.
+                        {0}[>]{1} and {0}[<]{1} can go all the way around the memory, so they
+                        run as normal loops rather than being sped up.
+
+    {0}-V{1}, {0}--version{1}       show the version of the interpreter.
+
+    {0}--visualize{1}         animate the memory blocks around the pointer as the code runs,
+                        one step at a time. The output is shown once it finishes.
+                        This does nothing if there isn't a terminal to draw in.
+
+    {0}--watch{1}             run the code, then run it again every time one of its files
+                        is changed, until the interpreter is stopped with Ctrl+C.
+
+    {0}--wrap{1} <mode>       which instructions wrap memory blocks around their range
+                        (eg. {0}0-255{1}) instead of throwing an error:
+
+                          {0}none{1}  neither of them. (default)
+                          {0}inc{1}   {0}+{1}, instead of throwing an {2}OverflowError{1}.
+                          {0}dec{1}   {0}-{1}, instead of throwing a {2}SubZeroError{1}.
+                          {0}both{1}  both of them.
+
+    {0}--wrap-cells{1}        wrap memory blocks around their range (eg. {0}0-255{1}) instead of throwing
+                        an {2}OverflowError{1} or {2}SubZeroError{1}. The same as {0}--wrap both{1}.
+
+    {0}--wrap-tape{1}         wrap the pointer around from the last memory block to the first
+                        (and back) instead of throwing an {2}OutOfBoundsError{1}.
+
+Extra Details
+-------------
+
+- Brainfuck files can be documented with Rust-style comments that get stripped out before execution.
+  Any special characters that are usually used in brainfuck execution, when commented, are ignored.
+  Any other text that isn't an instruction is ignored as well, unless {0}--strict{1} is used.
+
+- Whitespace is also stripped before execution, meaning whitespace is {2}irrelevant{1} to code execution.
+
+- The memory is {0}30,000 blocks{1} unless {0}--memory{1} is used, and each block {2}cannot{1} exceed the inclusive range of {0}0-255{1},
+  unless {0}--wrap-cells{1} is used. A larger range can be picked with {0}--cell-size{1}.
+
+- All 8 instructions are the same.\n", colour(CYAN), colour(WHITE), colour(RED));
     exit(0)
 }
 
-fn throw_exception_with_pos(error_name: &str, error_position: i32, error_message: &str) -> () {
-    println!("{}{}: at position {} - {}{}", RED, error_name, error_position, error_message, WHITE);
-    exit(0)
+// Reads "," input from the keyboard, one keypress at a time. Input is
+// byte-oriented, so a keypress outside of ASCII is given out as its UTF-8
+// bytes, one for each ",".
+#[derive(Default)]
+struct TerminalInput {
+    // The bytes of the last keypress that haven't been read yet.
+    pending: Vec<u8>
+}
+
+impl Read for TerminalInput {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        if self.pending.is_empty() {
+            let input_char = match Term::stdout().read_char() {
+                Ok(input_char) => input_char,
+
+                // Ctrl-C gets read as a keypress while waiting for one.
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {
+                    INTERRUPTED.store(true, Ordering::Relaxed);
+                    return Ok(0);
+                }
+
+                Err(_) => return Ok(0)
+            };
+
+            self.pending.extend_from_slice(input_char.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+
+        buffer[0] = self.pending.remove(0);
+
+        Ok(1)
+    }
+}
+
+fn print_error(error: &BrainfuckError) {
+    let position = error.position();
+
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        let (file, line) = match source_file(position) {
+            Some((file, line)) => (format!("\"file\": {}, ", json_string(file)), line),
+            None => (String::new(), position.line)
+        };
+
+        eprintln!(
+            "{{\"error\": {}, {}\"position\": {}, \"line\": {}, \"column\": {}, \"message\": {}}}",
+            json_string(error.name()), file, position.index, line, position.column, json_string(&error.message())
+        );
+    }
+    else {
+        eprintln!("{}{}: at {} - {}{}", colour(RED), error.name(), location(position), error.message(), colour(WHITE));
+    }
+}
+
+// Which of the files run as one the position is in,
+// along with the line it's on in that file.
+fn source_file(position: Position) -> Option<(&'static str, usize)> {
+    let (file, first_line) = SOURCE_FILES.get()?
+        .iter()
+        .rev()
+        .find(|(_, first_line)| *first_line <= position.line)?;
+
+    Some((file, position.line - first_line + 1))
+}
+
+// A position as it's shown in messages, eg. "line 3, column 5", with
+// the file it's in as well when several files are run as one.
+fn location(position: Position) -> String {
+    match source_file(position) {
+        Some((file, line)) => format!("\"{}\" line {}, column {}", file, line, position.column),
+        None => format!("line {}, column {}", position.line, position.column)
+    }
+}
+
+// Parses the value given after a flag, which has to be a whole number above 0.
+fn parse_positive_arg(flag: &str, value: Option<&String>) -> usize {
+    match value {
+        Some(value) => match value.parse::<usize>() {
+            Ok(number) if number > 0 => number,
+            _ => throw_exception("ArgumentError", &format!("expected a positive integer for {} - received \"{}\".", flag, value))
+        },
+        None => throw_exception("ArgumentError", &format!("expected a positive integer after {}.", flag))
+    }
+}
+
+// Set the options for a --compat preset, returning false if there isn't one with the name.
+fn apply_compat_preset(preset: &str, options: &mut Options) -> bool {
+    let (cell_size, eof_mode, memory_size) = match preset {
+        "classic" => (CellSize::Eight, EofMode::Unchanged, 30_000),
+        "beef" => (CellSize::Eight, EofMode::Zero, 30_000),
+        "bfdev" => (CellSize::ThirtyTwo, EofMode::Max, 65_536),
+        _ => return false
+    };
+
+    options.cell_size = cell_size;
+    options.eof_mode = eof_mode;
+    options.memory_size = memory_size;
+    options.wrap_increments = true;
+    options.wrap_decrements = true;
+
+    true
+}
+
+// Brainfuck only needs the 8 instruction characters, so any bytes that
+// aren't valid UTF-8 (eg. in comments) are replaced instead of refused,
+// and a byte order mark at the start is dropped.
+fn decode_code(code_bytes: &[u8]) -> String {
+    let code_bytes = code_bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(code_bytes);
+
+    String::from_utf8_lossy(code_bytes).into_owned()
+}
+
+// Prints a warning for anything in the code that is most likely a mistake.
+fn show_lint_warnings(code: &str) {
+    let brainfuck_code = sanitise_code(code)
+        .unwrap_or_else(|error| throw_error(error));
+
+    for warning in lint_code(&brainfuck_code) {
+        let position = warning.position();
+
+        eprintln!("{}Warning: at {} - {}{}", colour(CYAN), location(position), warning.message(), colour(WHITE));
+    }
+}
+
+// Print the ops the interpreter would run for the code, one on each line
+// with the line and column of the instruction it starts at.
+fn show_ir(code: &str, settings: &RunSettings, options: Options) {
+    let brainfuck_code = sanitise_code(code)
+        .unwrap_or_else(|error| throw_error(error));
+
+    let instructions = parse_code(&brainfuck_code)
+        .unwrap_or_else(|error| throw_error(error));
+
+    let optimised = optimise_code(&instructions, options);
+    let mut output = output_target(settings);
+
+    for (i, (op, code_index)) in optimised.ops.iter().zip(&optimised.indexes).enumerate() {
+        let position = brainfuck_code.positions[*code_index];
+
+        let description = match op {
+            Op::JumpIfZero(end) => format!("JumpIfZero -> {}", end),
+            Op::JumpIfNotZero(start) => format!("JumpIfNotZero -> {}", start),
+            op => format!("{:?}", op)
+        };
+
+        if writeln!(output, "{:>6}  {:<24}{}", i, description, location(position)).is_err() {
+            throw_exception("OutputError", "could not write the ops to the output.");
+        }
+    }
+}
+
+fn throw_error(error: BrainfuckError) -> ! {
+    print_error(&error);
+    exit(1)
+}
+
+// Random bytes for "," to read, from a SplitMix64 generator, so the
+// same seed gives the same bytes on any machine.
+struct RandomInput {
+    state: u64
+}
+
+impl RandomInput {
+    fn next_number(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut number = self.state;
+
+        number = (number ^ (number >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        number = (number ^ (number >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        number ^ (number >> 31)
+    }
+}
+
+impl Read for RandomInput {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        // Every byte gets a number of its own, rather than splitting
+        // numbers up, so the bytes don't depend on how many are read at once.
+        for byte in buffer.iter_mut() {
+            *byte = self.next_number() as u8;
+        }
+
+        Ok(buffer.len())
+    }
+}
+
+// The seed given with --seed, or one from the time that gets shown
+// so the same random input can be given again.
+fn random_seed(settings: &RunSettings) -> u64 {
+    settings.seed.unwrap_or_else(|| {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or(0);
+
+        eprintln!("{}Random input seed: {}{}", colour(CYAN), seed, colour(WHITE));
+        seed
+    })
+}
+
+// Read random bytes with --random-input, or from the text given with --stdin or
+// the file given with --input if there is one. Otherwise, wait for a keypress when
+// running interactively, or take the bytes of whatever was piped into stdin.
+fn input_source(settings: &RunSettings) -> Box<dyn Read> {
+    if settings.random_input {
+        return Box::new(RandomInput { state: random_seed(settings) });
+    }
+
+    if let Some(input_text) = &settings.input_text {
+        return Box::new(io::Cursor::new(input_text.clone().into_bytes()));
+    }
+
+    if let Some(input_path) = &settings.input_path {
+        return match fs::File::open(input_path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(error) => throw_exception("FileLoadError", &format!("cannot open the input file \"{}\". ({})", input_path, error))
+        };
+    }
+
+    if io::stdin().is_terminal() {
+        Box::new(TerminalInput::default())
+    }
+    else {
+        Box::new(io::stdin())
+    }
+}
+
+// Input piped into stdin that warns the first time "," finds there's nothing in
+// it at all, since the piped input is most likely missing.
+struct EmptyInputWarning {
+    input: io::Stdin,
+    eof_mode: EofMode,
+    has_read: bool
+}
+
+impl Read for EmptyInputWarning {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.input.read(buffer)?;
+
+        if bytes_read == 0 && !self.has_read {
+            let (eof_behaviour, eof_mode) = match self.eof_mode {
+                EofMode::Unchanged => ("leaves the memory block as it is", "unchanged"),
+                EofMode::Zero => ("sets the memory block to 0", "zero"),
+                EofMode::Max => ("sets the memory block to its largest value", "max")
+            };
+
+            eprintln!(
+                "{}Warning: the code asked for input, but none was given with --input, --stdin or a pipe, so \",\" {} (--eof {}).{}",
+                colour(CYAN), eof_behaviour, eof_mode, colour(WHITE)
+            );
+        }
+
+        self.has_read = true;
+
+        Ok(bytes_read)
+    }
+}
+
+// The input for running code, which warns about empty piped input unless --quiet is used.
+fn program_input(settings: &RunSettings, options: Options) -> Box<dyn Read> {
+    let reads_stdin = !settings.random_input && settings.input_text.is_none() && settings.input_path.is_none() && !io::stdin().is_terminal();
+
+    if reads_stdin && !settings.quiet {
+        Box::new(EmptyInputWarning { input: io::stdin(), eof_mode: options.eof_mode, has_read: false })
+    }
+    else {
+        input_source(settings)
+    }
+}
+
+// Write to the file given with -o if there is one, otherwise to stdout.
+fn output_target(settings: &RunSettings) -> Box<dyn Write> {
+    match &settings.output_path {
+        Some(output_path) => match fs::File::create(output_path) {
+            Ok(file) => Box::new(file),
+            Err(error) => throw_exception("OutputError", &format!("cannot create the output file \"{}\". ({})", output_path, error))
+        },
+        None => Box::new(io::stdout().lock())
+    }
+}
+
+// The language the code is written in, which gets translated into brainfuck before running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Dialect {
+    #[default]
+    Brainfuck,
+    Ook
+}
+
+// How newlines written by "." are changed before they reach the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum OutputNewline {
+    #[default]
+    Lf,
+    Crlf,
+    StripCr
+}
+
+// Output that changes the newlines written to it before passing them on.
+struct NewlineOutput {
+    output: Box<dyn Write>,
+    newline: OutputNewline
+}
+
+impl Write for NewlineOutput {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let mut translated = Vec::with_capacity(buffer.len());
+
+        for byte in buffer {
+            match (self.newline, byte) {
+                (OutputNewline::Crlf, b'\n') => translated.extend_from_slice(b"\r\n"),
+                (OutputNewline::StripCr, b'\r') => {}
+                _ => translated.push(*byte)
+            }
+        }
+
+        self.output.write_all(&translated)?;
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+// How the bytes written by "." are turned into text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Encoding {
+    Ascii,
+    #[default]
+    Latin1,
+    Utf8
+}
+
+// Output that checks the bytes written to it are text in the encoding before
+// passing them on. A UTF-8 character can be written a byte at a time, so the
+// bytes of one that hasn't been finished yet are held back until it is.
+struct EncodedOutput {
+    output: Box<dyn Write>,
+    encoding: Encoding,
+    pending: Vec<u8>
+}
+
+impl Write for EncodedOutput {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        if self.encoding == Encoding::Ascii {
+            let text: Vec<u8> = buffer.iter().map(|byte| if byte.is_ascii() { *byte } else { b'?' }).collect();
+
+            self.output.write_all(&text)?;
+
+            return Ok(buffer.len());
+        }
+
+        self.pending.extend_from_slice(buffer);
+
+        let mut text = String::new();
+
+        loop {
+            let error = match std::str::from_utf8(&self.pending) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    self.pending.clear();
+                    break;
+                }
+
+                Err(error) => error
+            };
+
+            let valid_up_to = error.valid_up_to();
+
+            text.push_str(&String::from_utf8_lossy(&self.pending[..valid_up_to]));
+
+            match error.error_len() {
+                Some(invalid_len) => {
+                    text.push(char::REPLACEMENT_CHARACTER);
+                    self.pending.drain(..valid_up_to + invalid_len);
+                }
+
+                // The rest is the start of a character that could still be finished.
+                None => {
+                    self.pending.drain(..valid_up_to);
+                    break;
+                }
+            }
+        }
+
+        self.output.write_all(text.as_bytes())?;
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+// A character that was never finished still gets shown as one that isn't UTF-8.
+impl Drop for EncodedOutput {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            let _ = self.output.write_all(char::REPLACEMENT_CHARACTER.to_string().as_bytes());
+            let _ = self.output.flush();
+        }
+    }
+}
+
+// The output for running code, with its encoding checked if --encoding is
+// used, and its newlines changed if --output-newline is used.
+fn program_output(settings: &RunSettings) -> Box<dyn Write> {
+    let output = match settings.encoding {
+        Encoding::Latin1 => output_target(settings),
+        encoding => Box::new(EncodedOutput { output: output_target(settings), encoding, pending: Vec::new() })
+    };
+
+    match settings.output_newline {
+        OutputNewline::Lf => output,
+        newline => Box::new(NewlineOutput { output, newline })
+    }
+}
+
+// How the value of each memory block is shown in the memory breakdown and window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum CellDisplay {
+    Char,
+    #[default]
+    Dec,
+    Hex
+}
+
+// Settings that only change how the executable runs code and shows
+// what happened, rather than how the code itself behaves.
+#[derive(Default, Clone)]
+struct RunSettings {
+    show_memory_after: bool,
+    step_through: bool,
+
+    // Positions in the sanitised code to pause at.
+    breakpoints: Vec<usize>,
+
+    // A file to read "," input from, instead of stdin.
+    input_path: Option<String>,
+
+    // Text to read "," input from, instead of stdin.
+    input_text: Option<String>,
+
+    // A file to write "." output to, instead of stdout.
+    output_path: Option<String>,
+
+    // Translate the code into another language instead of running it.
+    emit_target: Option<EmitTarget>,
+
+    // A file to write the memory to as JSON once the code has run.
+    memory_dump_path: Option<String>,
+
+    // Leave out the blank lines around the output,
+    // and the message when there isn't any.
+    quiet: bool,
+
+    // A file to write a line to for every instruction that runs.
+    trace_path: Option<String>,
+
+    // Show this many memory blocks on either side of the pointer
+    // after execution, instead of the memory breakdown.
+    memory_window_size: Option<usize>,
+
+    // Warn about code that is most likely a mistake before running it.
+    lint: bool,
+
+    // Reformat the code instead of running it, with at
+    // most this many instructions on each line.
+    format_width: Option<usize>,
+
+    // Print code that prints this text, instead of running any.
+    generate_text: Option<String>,
+
+    // Run the built-in program instead, to check it prints what it should.
+    hello: bool,
+
+    // Print the sanitised code instead of running it.
+    minify: bool,
+
+    // Print the ops the code is turned into instead of running it.
+    dump_ir: bool,
+
+    // Time running the code this many times, instead of running it once.
+    bench_iterations: Option<usize>,
+
+    // Run the code without any input or output, only counting its steps.
+    count_only: bool,
+
+    dialect: Dialect,
+    cell_display: CellDisplay,
+    output_newline: OutputNewline,
+    encoding: Encoding,
+
+    // Show memory blocks that are 0 in the memory breakdown too.
+    show_zeros: bool,
+
+    // Run the code again whenever one of its files changes.
+    watch: bool,
+
+    // Animate the memory around the pointer, running this many steps a second.
+    frame_rate: Option<u32>,
+
+    // The memory block the pointer starts on.
+    tape_offset: Option<usize>,
+
+    // Run every program in this directory and check what they output.
+    test_dir: Option<String>,
+
+    // Run every program in this directory, one after another.
+    playlist_dir: Option<String>,
+
+    // Take the input from after a "!" in the code.
+    bang_input: bool,
+
+    // Read random bytes as input, from a generator started with the seed.
+    random_input: bool,
+    seed: Option<u64>,
+
+    // Show a line about what the code did once it has run.
+    show_stats: bool,
+
+    // Files to carry on running from, and to save where the run got up to.
+    load_state_path: Option<String>,
+    save_state_path: Option<String>,
+
+    // The characters used for each instruction instead of the usual ones, in
+    // the order "+-<>[].,".
+    mapping: Option<[char; 8]>
+}
+
+// How many times --bench runs the code unless it's told otherwise.
+const DEFAULT_BENCH_ITERATIONS: usize = 10;
+
+// How many instructions --format puts on a line unless it's told otherwise.
+const DEFAULT_FORMAT_WIDTH: usize = 40;
+
+// The program --hello runs, which goes through while loops inside of
+// while loops and a "[<]" to get to its output.
+const HELLO_WORLD_CODE: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+const HELLO_WORLD_OUTPUT: &[u8] = b"Hello World!\n";
+
+// How many steps --visualize shows a second unless it's told otherwise.
+const DEFAULT_FRAME_RATE: u32 = 20;
+
+// How many memory blocks --visualize shows on either side of the pointer.
+const VISUALIZER_WINDOW_SIZE: usize = 8;
+
+// How many characters of the latest line of output --visualize shows.
+const VISUALIZER_OUTPUT_WIDTH: usize = 60;
+
+// How many loops are listed when profiling, starting with the one that ran the most.
+const HOTTEST_LOOPS_SHOWN: usize = 10;
+
+// How often --watch checks whether the files have changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// How many instructions the debugger shows on either side of the next one.
+const DEBUGGER_CODE_WINDOW_SIZE: usize = 30;
+
+// How many memory blocks the debugger shows on either side of the pointer.
+const DEBUGGER_WINDOW_SIZE: usize = 4;
+
+const DEBUGGER_HELP: &str = "Press Enter to run the next instruction, or type a number to run that many.
+Type 'p' to run until the pointer moves, 'c' to continue to the end, or 'q' to quit.
+With --reverse-debug, type 'b' to undo the last instruction.";
+
+// Shows the value of a memory block the way that was asked for with --cell-display,
+// where characters that can't be printed are shown as a ".". That includes values
+// from larger memory blocks that aren't characters at all, like surrogates.
+fn cell_repr(interpreter: &Interpreter, value: u32, cell_display: CellDisplay) -> String {
+    match cell_display {
+        CellDisplay::Char => match char::from_u32(value) {
+            Some(ch) if ch.is_ascii_graphic() || ch == ' ' => ch.to_string(),
+            _ => ".".to_string()
+        },
+
+        CellDisplay::Dec => interpreter.options().display_value(value).to_string(),
+
+        CellDisplay::Hex => format!("0x{:02x}", value)
+    }
+}
+
+fn show_memory_breakdown(interpreter: &Interpreter, settings: &RunSettings) {
+    let memory = interpreter.memory();
+    let furthest_ptr = interpreter.origin().saturating_add_signed(interpreter.furthest_pointer());
+    let ptr = interpreter.pointer_index();
+
+    // With --heatmap, every memory block that was touched is shown, even
+    // if it ended up back at 0, along with how many times it was.
+    let cell_accesses = interpreter.cell_accesses();
+    let most_accesses = cell_accesses.iter().copied().max().unwrap_or(0);
+
+    let mut locations_to_values = String::new();
+
+    for (i, value) in memory.iter().enumerate().take(furthest_ptr + 1) {
+        let accesses = cell_accesses.get(i).copied().unwrap_or(0);
+
+        if *value > 0 || accesses > 0 || settings.show_zeros {
+            let mem_block_repr = &*cell_repr(interpreter, *value, settings.cell_display);
+            let mem_block_pos = &*interpreter.block_number(i).to_string();
+            
+            locations_to_values.push_str(
+                &format!(
+                    "{}{: >7}{} - {}[{}]{}",
+                    colour(CYAN),
+                    mem_block_pos,
+                    colour(WHITE),
+                    colour(GREEN),
+                    mem_block_repr,
+                    colour(WHITE)
+                )
+            );
+
+            if interpreter.options().count_accesses {
+                locations_to_values.push_str(&format!("  {}{} accesses{}", heat_colour(accesses, most_accesses), accesses, colour(WHITE)));
+            }
+
+            locations_to_values.push('\n');
+        }
+    }
+
+    locations_to_values.push_str(&format!("\n    ptr => {1}{2}{0}", colour(WHITE), colour(CYAN), interpreter.block_number(ptr)));
+
+    eprintln!("\n Memory Breakdown\n------------------\n{}", locations_to_values);
+}
+
+// A shade of red that gets brighter the closer the accesses are to the most
+// that any memory block had, for the heatmap.
+fn heat_colour(accesses: usize, most_accesses: usize) -> String {
+    if !COLOURS_ENABLED.load(Ordering::Relaxed) || most_accesses == 0 {
+        return String::new();
+    }
+
+    let heat = (accesses * 175 / most_accesses) as u8;
+
+    format!("\u{001b}[38;2;{};80;80m", 80 + heat)
+}
+
+// Writes the pointer and the non-zero memory blocks to a file as JSON, eg.
+// {"ptr": 3, "cells": {"0": 72, "1": 101}}
+fn dump_memory(interpreter: &Interpreter, dump_path: &str) {
+    let cells: Vec<String> = interpreter.nonzero_cells()
+        .map(|(block, value)| format!("\"{}\": {}", block, interpreter.options().display_value(value)))
+        .collect();
+
+    let json = format!("{{\"ptr\": {}, \"cells\": {{{}}}}}\n", interpreter.pointer(), cells.join(", "));
+
+    if let Err(error) = fs::write(dump_path, json) {
+        throw_exception("OutputError", &format!("cannot write the memory to \"{}\". ({})", dump_path, error));
+    }
+}
+
+// Shows the next instruction and the memory blocks around the pointer.
+fn show_debugger_state(interpreter: &Interpreter, cell_display: CellDisplay) {
+    let code_index = interpreter.code_index();
+    let position = interpreter.position(code_index);
+
+    eprintln!(
+        "\n{}step {}{} - instruction {}'{}'{} at position {}{}{} ({})",
+        colour(CYAN), interpreter.steps() + 1, colour(WHITE),
+        colour(GREEN), interpreter.code()[code_index], colour(WHITE),
+        colour(CYAN), code_index, colour(WHITE),
+        location(position)
+    );
+
+    // The code around the next instruction, with a caret underneath it.
+    let code = interpreter.code();
+    let first_index = code_index.saturating_sub(DEBUGGER_CODE_WINDOW_SIZE);
+    let last_index = code_index.saturating_add(DEBUGGER_CODE_WINDOW_SIZE + 1).min(code.len());
+
+    let before = if first_index > 0 { "..." } else { "" };
+    let after = if last_index < code.len() { "..." } else { "" };
+    let shown_code: String = code[first_index..last_index].iter().collect();
+
+    eprintln!("    {}{}{}", before, shown_code, after);
+    eprintln!("    {}{}^{}", " ".repeat(before.len() + code_index - first_index), colour(GREEN), colour(WHITE));
+
+    show_memory_window(interpreter, DEBUGGER_WINDOW_SIZE, cell_display);
+}
+
+// Shows the given number of memory blocks on either side of the pointer, including
+// any that haven't been allocated yet, since those are implicitly 0.
+fn show_memory_window(interpreter: &Interpreter, window_size: usize, cell_display: CellDisplay) {
+    let ptr = interpreter.pointer_index();
+    let memory = interpreter.memory();
+
+    let first_block = ptr.saturating_sub(window_size);
+    let last_block = ptr.saturating_add(window_size).min(interpreter.origin() + interpreter.options().memory_size - 1);
+
+    for i in first_block..=last_block {
+        let marker = if i == ptr { "ptr =>" } else { "" };
+        let value = cell_repr(interpreter, memory.get(i).copied().unwrap_or(0), cell_display);
+
+        eprintln!("{: >6}{}{: >7}{} - {}[{}]{}", marker, colour(CYAN), interpreter.block_number(i), colour(WHITE), colour(GREEN), value, colour(WHITE));
+    }
+}
+
+// Shows where a "#" debug instruction was reached and the memory around it.
+// The code index is the one the "#" was at, from before it was stepped over.
+fn show_debug_char(interpreter: &Interpreter, code_index: usize, output: &mut impl Write, cell_display: CellDisplay) {
+    let _ = output.flush();
+
+    let position = interpreter.position(code_index);
+
+    eprintln!("\n{}#{} reached at {}", colour(CYAN), colour(WHITE), location(position));
+    show_memory_window(interpreter, DEBUGGER_WINDOW_SIZE, cell_display);
+}
+
+// Runs a single step, writing a line about it to the trace if there is one and
+// stopping to show the memory if it was a "#" debug instruction.
+fn run_step<W: Write>(interpreter: &mut Interpreter, output: &mut W, trace: &mut Option<BufWriter<fs::File>>, cell_display: CellDisplay) -> Result<bool, BrainfuckError> {
+    let step = interpreter.steps() + 1;
+    let code_index = interpreter.code_index();
+    let block_before = interpreter.pointer();
+    let value_before = interpreter.options().display_value(interpreter.memory()[interpreter.pointer_index()]);
+
+    let has_stepped = interpreter.step(output)?;
+
+    if let Some(trace) = trace {
+        if has_stepped {
+            // The memory block the step started on, which has been moved
+            // along if any memory was added to the left of it.
+            let ptr = (block_before + interpreter.origin() as isize) as usize;
+
+            let _ = writeln!(
+                trace, "{}\t{}\tptr {} -> {}\tcell {} -> {}",
+                step, interpreter.code()[code_index], block_before, interpreter.pointer(), value_before, interpreter.options().display_value(interpreter.memory()[ptr])
+            );
+        }
+    }
+
+    if interpreter.reached_debug_char() {
+        show_debug_char(interpreter, code_index, output, cell_display);
+    }
+
+    Ok(has_stepped)
+}
+
+// Runs the code to the end.
+fn run_code<W: Write>(interpreter: &mut Interpreter, output: &mut W, trace: &mut Option<BufWriter<fs::File>>, cell_display: CellDisplay) -> Result<(), BrainfuckError> {
+    loop {
+        if INTERRUPTED.load(Ordering::Relaxed) || !run_step(interpreter, output, trace, cell_display)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+// Runs the code one instruction at a time, pausing for a command before
+// each one (or only at breakpoints) so the memory can be watched.
+fn run_debugger<W: Write>(interpreter: &mut Interpreter, output: &mut W, trace: &mut Option<BufWriter<fs::File>>, settings: &RunSettings) -> Result<(), BrainfuckError> {
+    let mut steps_to_run: usize = 0;
+    let mut pointer_to_leave: Option<isize> = None;
+    let mut continuing = !settings.step_through;
+    let mut has_shown_help = false;
+
+    while !interpreter.is_finished() {
+        let at_breakpoint = settings.breakpoints.contains(&interpreter.code_index());
+
+        let paused = if at_breakpoint {
+            let _ = output.flush();
+
+            eprintln!("\n{}Breakpoint{} reached at position {}{}{}", colour(RED), colour(WHITE), colour(CYAN), interpreter.code_index(), colour(WHITE));
+            show_memory_breakdown(interpreter, settings);
+
+            continuing = false;
+            true
+        }
+        else if continuing {
+            false
+        }
+        else if let Some(ptr) = pointer_to_leave {
+            interpreter.pointer() != ptr
+        }
+        else {
+            steps_to_run == 0
+        };
+
+        if paused {
+            pointer_to_leave = None;
+
+            if !has_shown_help {
+                eprintln!("{}", DEBUGGER_HELP);
+                has_shown_help = true;
+            }
+
+            show_debugger_state(interpreter, settings.cell_display);
+
+            loop {
+                eprint!("{}(step){} ", colour(CYAN), colour(WHITE));
+                let _ = io::stderr().flush();
+
+                // Treat a closed input the same as quitting.
+                let command = Term::stderr().read_line().unwrap_or_else(|_| "q".to_string());
+
+                match command.trim() {
+                    "" => steps_to_run = 1,
+
+                    "b" => {
+                        if !interpreter.options().record_history {
+                            eprintln!("Going back needs {0}--reverse-debug{1}.", colour(CYAN), colour(WHITE));
+                        }
+                        else if interpreter.step_back() {
+                            show_debugger_state(interpreter, settings.cell_display);
+                        }
+                        else {
+                            eprintln!("There is nothing left to undo.");
+                        }
+
+                        continue;
+                    }
+
+                    "c" => continuing = true,
+                    "p" => pointer_to_leave = Some(interpreter.pointer()),
+                    "q" => return Ok(()),
+
+                    command => match command.parse::<usize>() {
+                        Ok(steps) if steps > 0 => steps_to_run = steps,
+                        _ => {
+                            eprintln!("{}", DEBUGGER_HELP);
+                            continue;
+                        }
+                    }
+                }
+
+                break;
+            }
+        }
+
+        run_step(interpreter, output, trace, settings.cell_display)?;
+        steps_to_run = steps_to_run.saturating_sub(1);
+
+        let _ = output.flush();
+    }
+
+    Ok(())
+}
+
+// Draws the memory blocks around the pointer, with the pointer's highlighted, and the
+// latest line of output underneath, over the top of the last frame if there was one.
+fn draw_frame(term: &Term, interpreter: &Interpreter, output: &[u8], settings: &RunSettings, redraw: bool) {
+    let ptr = interpreter.pointer_index();
+    let memory = interpreter.memory();
+
+    let first_block = ptr.saturating_sub(VISUALIZER_WINDOW_SIZE);
+    let last_block = ptr.saturating_add(VISUALIZER_WINDOW_SIZE).min(interpreter.origin() + interpreter.options().memory_size - 1);
+
+    let mut positions = String::new();
+    let mut values = String::new();
+    let mut pointer = String::new();
+
+    for i in first_block..=last_block {
+        let value = cell_repr(interpreter, memory.get(i).copied().unwrap_or(0), settings.cell_display);
+        let value_colour = if i == ptr { colour(GREEN) } else { colour(WHITE) };
+
+        positions.push_str(&format!("{}{: >6}{}", colour(CYAN), interpreter.block_number(i), colour(WHITE)));
+        values.push_str(&format!("{}{: >6}{}", value_colour, value, colour(WHITE)));
+        pointer.push_str(if i == ptr { "     ^" } else { "      " });
+    }
+
+    let output = String::from_utf8_lossy(output);
+    let latest_line: Vec<char> = output.lines().last().unwrap_or("").chars().collect();
+    let shown_line: String = latest_line[latest_line.len().saturating_sub(VISUALIZER_OUTPUT_WIDTH)..].iter().collect();
+
+    if redraw {
+        let _ = term.clear_last_lines(4);
+    }
+
+    let _ = term.write_line(&positions);
+    let _ = term.write_line(&values);
+    let _ = term.write_line(&pointer);
+    let _ = term.write_line(&format!("output: {}", shown_line.escape_debug()));
+}
+
+// Runs the code a step at a time, drawing the memory after each one. The code's
+// output is held back until the end, so it doesn't get drawn over.
+fn run_visualizer<W: Write>(interpreter: &mut Interpreter, output: &mut W, trace: &mut Option<BufWriter<fs::File>>, settings: &RunSettings, frame_rate: u32) -> Result<(), BrainfuckError> {
+    let term = Term::stderr();
+    let frame_time = Duration::from_secs(1) / frame_rate;
+    let mut held_output = Vec::new();
+
+    draw_frame(&term, interpreter, &held_output, settings, false);
+
+    let result = loop {
+        match run_step(interpreter, &mut held_output, trace, settings.cell_display) {
+            Ok(true) if INTERRUPTED.load(Ordering::Relaxed) => break Ok(()),
+
+            Ok(true) => {
+                std::thread::sleep(frame_time);
+                draw_frame(&term, interpreter, &held_output, settings, true);
+            }
+
+            Ok(false) => break Ok(()),
+
+            Err(error) => break Err(error)
+        }
+    };
+
+    if output.write_all(&held_output).is_err() {
+        throw_exception("OutputError", "could not write the output.");
+    }
+
+    result
+}
+
+// Starts the pointer on the memory block given with --tape-offset, if there was one.
+fn apply_tape_offset(interpreter: &mut Interpreter, settings: &RunSettings) {
+    if let Some(tape_offset) = settings.tape_offset {
+        if !interpreter.set_pointer(tape_offset) {
+            throw_exception("ArgumentError", &format!("cannot start the pointer at {} with only {} memory blocks.", tape_offset, interpreter.options().memory_size));
+        }
+    }
+}
+
+// Carries on from a state saved with --save-state.
+fn load_state(interpreter: &mut Interpreter, state_path: &str) {
+    let state_bytes = match fs::read(state_path) {
+        Ok(state_bytes) => state_bytes,
+        Err(error) => throw_exception("FileLoadError", &format!("cannot read the state file \"{}\". ({})", state_path, error))
+    };
+
+    let state = match State::from_bytes(&state_bytes) {
+        Some(state) => state,
+        None => throw_exception("FileLoadError", &format!("\"{}\" is not a state file saved with --save-state.", state_path))
+    };
+
+    if !interpreter.restore_state(&state) {
+        throw_exception("FileLoadError", &format!("the state in \"{}\" was not saved from this code with these options.", state_path));
+    }
+}
+
+// Runs the code once for the command line, showing everything asked for, and
+// returns the error if it stops on one, after writing out the trace and state.
+// Everything shown apart from the output of the code goes to stderr, so the
+// output can be redirected to a file on its own.
+//
+// Anything that runs code more than once in the same process (like the REPL
+// and benchmarks) uses an `Interpreter` directly, which returns its errors,
+// takes its input and output as parameters, and keeps its own memory.
+fn execute_code(code: &str, settings: &RunSettings, options: Options) -> Result<(), BrainfuckError> {
+    if !settings.quiet {
+        eprintln!();
+    }
+
+    let mut interpreter = Interpreter::with_options(code, options)?;
+
+    interpreter.set_input(program_input(settings, options));
+    apply_tape_offset(&mut interpreter, settings);
+
+    if let Some(state_path) = &settings.load_state_path {
+        load_state(&mut interpreter, state_path);
+    }
+
+    // Buffer the output so each "." doesn't cost a write of its own,
+    // then flush it all before anything else gets printed.
+    let mut output = BufWriter::new(program_output(settings));
+
+    let mut trace = settings.trace_path.as_ref().map(|trace_path| match fs::File::create(trace_path) {
+        Ok(file) => BufWriter::new(file),
+        Err(error) => throw_exception("OutputError", &format!("cannot create the trace file \"{}\". ({})", trace_path, error))
+    });
+
+    let start_time = Instant::now();
+    // Animating needs a terminal to draw in, otherwise the code just runs.
+    let frame_rate = settings.frame_rate.filter(|_| Term::stderr().is_term());
+
+    let result = if settings.step_through || !settings.breakpoints.is_empty() {
+        run_debugger(&mut interpreter, &mut output, &mut trace, settings)
+    }
+    else if let Some(frame_rate) = frame_rate {
+        catch_interrupts();
+        run_visualizer(&mut interpreter, &mut output, &mut trace, settings, frame_rate)
+    }
+    else {
+        catch_interrupts();
+        run_code(&mut interpreter, &mut output, &mut trace, settings.cell_display)
+    };
+    let elapsed_time = start_time.elapsed();
+
+    let _ = output.flush();
+    drop(output);
+
+    // Errors exit straight away, so the trace and state have to be written out first.
+    if let Some(trace) = &mut trace {
+        let _ = trace.flush();
+    }
+
+    if let Some(state_path) = &settings.save_state_path {
+        if let Err(error) = fs::write(state_path, interpreter.state().to_bytes()) {
+            throw_exception("OutputError", &format!("cannot write the state to \"{}\". ({})", state_path, error));
+        }
+    }
+
+    result?;
+
+    // Show what the code got up to before it was stopped, as it
+    // could have been running for a while.
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        eprintln!("\n{}Interrupted{} - execution was stopped with Ctrl-C after {} steps.", colour(RED), colour(WHITE), interpreter.steps());
+
+        if settings.show_memory_after {
+            show_memory_breakdown(&interpreter, settings);
+        }
+
+        exit(130);
+    }
+
+    if !settings.quiet {
+        if !interpreter.has_output() {
+            eprintln!("{}No output provided.{}", colour(RED), colour(WHITE));
+        }
+
+        eprintln!();
+    }
+
+    if settings.show_stats {
+        eprintln!(
+            "{}Stats{} - {}{}{} steps, {}{}{} bytes of output, {}{}{} bytes of input, furthest pointer {}{}{}",
+            colour(CYAN), colour(WHITE),
+            colour(GREEN), interpreter.steps(), colour(WHITE),
+            colour(GREEN), interpreter.output_bytes(), colour(WHITE),
+            colour(GREEN), interpreter.input_bytes(), colour(WHITE),
+            colour(GREEN), interpreter.furthest_pointer(), colour(WHITE)
+        );
+    }
+
+    if settings.show_memory_after {
+        match settings.memory_window_size {
+            Some(window_size) => {
+                eprintln!("\n Memory Window\n---------------");
+                show_memory_window(&interpreter, window_size, settings.cell_display);
+                eprintln!();
+            }
+
+            None => show_memory_breakdown(&interpreter, settings)
+        }
+    }
+
+    if let Some(dump_path) = &settings.memory_dump_path {
+        dump_memory(&interpreter, dump_path);
+    }
+
+    if options.profile {
+        let mut instruction_counts = String::new();
+
+        for (instruction, count) in interpreter.instruction_counts() {
+            instruction_counts.push_str(&format!("{}{: >7}{} - {}[{}]{}\n", colour(CYAN), instruction, colour(WHITE), colour(GREEN), count, colour(WHITE)));
+        }
+
+        instruction_counts.push_str(&format!("\n{}{: >7}{} - {}[{}]{}\n", colour(CYAN), "total", colour(WHITE), colour(GREEN), interpreter.steps(), colour(WHITE)));
+        instruction_counts.push_str(&format!("{}{: >7}{} - {}{:?}{}", colour(CYAN), "time", colour(WHITE), colour(GREEN), elapsed_time, colour(WHITE)));
+
+        eprintln!("\n Profile\n---------\n{}\n", instruction_counts);
+
+        let mut loop_iterations = interpreter.loop_iterations();
+
+        if !loop_iterations.is_empty() {
+            loop_iterations.sort_by_key(|&(_, iterations)| std::cmp::Reverse(iterations));
+
+            let mut hottest_loops = String::new();
+
+            for (code_index, iterations) in loop_iterations.iter().take(HOTTEST_LOOPS_SHOWN) {
+                let position = interpreter.position(*code_index);
+                let location = location(position);
+
+                hottest_loops.push_str(&format!("{}{: >24}{} - {}[{}]{}\n", colour(CYAN), location, colour(WHITE), colour(GREEN), iterations, colour(WHITE)));
+            }
+
+            eprintln!(" Hottest Loops\n---------------\n{}", hottest_loops);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs the code the given number of times without showing its output, then
+// shows how long it took. Every run gets the same input.
+fn run_benchmark(code: &str, settings: &RunSettings, options: Options, iterations: usize) {
+    let mut input_bytes = Vec::new();
+
+    // Random input never runs out, so rather than reading it all up
+    // front, every run gets its own from the same seed.
+    let random_seed = settings.random_input.then(|| random_seed(settings));
+
+    // Waiting on the keyboard would get timed as well, so
+    // only input that was given up front is used.
+    if random_seed.is_none() && (settings.input_text.is_some() || settings.input_path.is_some() || !io::stdin().is_terminal()) {
+        if let Err(error) = input_source(settings).read_to_end(&mut input_bytes) {
+            throw_exception("FileLoadError", &format!("cannot read the input. ({})", error));
+        }
+    }
+
+    let mut elapsed_time = Duration::ZERO;
+    let mut total_steps = 0;
+
+    for _ in 0..iterations {
+        let mut interpreter = Interpreter::with_options(code, options)
+            .unwrap_or_else(|error| throw_error(error));
+
+        match random_seed {
+            Some(seed) => interpreter.set_input(Box::new(RandomInput { state: seed })),
+            None => interpreter.set_input(Box::new(io::Cursor::new(input_bytes.clone())))
+        }
+
+        apply_tape_offset(&mut interpreter, settings);
+
+        let start_time = Instant::now();
+        let result = interpreter.run_with_output(&mut io::sink());
+        elapsed_time += start_time.elapsed();
+
+        if let Err(error) = result {
+            throw_error(error);
+        }
+
+        total_steps += interpreter.steps();
+    }
+
+    let steps_per_second = total_steps as f64 / elapsed_time.as_secs_f64();
+
+    let mut benchmark = String::new();
+
+    benchmark.push_str(&format!("{}{: >7}{} - {}[{}]{}\n", colour(CYAN), "runs", colour(WHITE), colour(GREEN), iterations, colour(WHITE)));
+    benchmark.push_str(&format!("{}{: >7}{} - {}{:?}{}\n", colour(CYAN), "total", colour(WHITE), colour(GREEN), elapsed_time, colour(WHITE)));
+    benchmark.push_str(&format!("{}{: >7}{} - {}{:?}{}\n", colour(CYAN), "average", colour(WHITE), colour(GREEN), elapsed_time.div_f64(iterations as f64), colour(WHITE)));
+    benchmark.push_str(&format!("{}{: >7}{} - {}[{}]{}\n", colour(CYAN), "steps", colour(WHITE), colour(GREEN), total_steps / iterations, colour(WHITE)));
+    benchmark.push_str(&format!("{}{: >7}{} - {}[{:.0}]{}", colour(CYAN), "steps/s", colour(WHITE), colour(GREEN), steps_per_second, colour(WHITE)));
+
+    println!("\n Benchmark\n-----------\n{}\n", benchmark);
+}
+
+// Runs the built-in program with the options given, showing what it printed,
+// and exits with an error if that isn't what it's meant to print.
+fn run_hello(options: Options) {
+    let result = Interpreter::with_options(HELLO_WORLD_CODE, options)
+        .and_then(|mut interpreter| interpreter.run());
+
+    match result {
+        Ok(output) if output == HELLO_WORLD_OUTPUT => {
+            print!("{}", String::from_utf8_lossy(&output));
+            println!("{}PASS{} the built-in program printed what it should.", colour(GREEN), colour(WHITE));
+        }
+
+        Ok(output) => {
+            print!("{}", String::from_utf8_lossy(&output));
+            println!("{}FAIL{} the built-in program should have printed {:?}.", colour(RED), colour(WHITE), String::from_utf8_lossy(HELLO_WORLD_OUTPUT));
+            exit(1);
+        }
+
+        Err(error) => throw_error(error)
+    }
+}
+
+// Runs the code once with nothing to read and nowhere to write to,
+// then shows how many steps it took and how long.
+fn run_count_only(code: &str, settings: &RunSettings, options: Options) {
+    let options = Options { eof_mode: EofMode::Zero, ..options };
+
+    let mut interpreter = Interpreter::with_options(code, options)
+        .unwrap_or_else(|error| throw_error(error));
+
+    apply_tape_offset(&mut interpreter, settings);
+
+    let start_time = Instant::now();
+    let result = interpreter.run_with_output(&mut io::sink());
+    let elapsed_time = start_time.elapsed();
+
+    if let Err(error) = result {
+        throw_error(error);
+    }
+
+    let mut counts = String::new();
+
+    counts.push_str(&format!("{}{: >7}{} - {}[{}]{}\n", colour(CYAN), "steps", colour(WHITE), colour(GREEN), interpreter.steps(), colour(WHITE)));
+    counts.push_str(&format!("{}{: >7}{} - {}{:?}{}", colour(CYAN), "time", colour(WHITE), colour(GREEN), elapsed_time, colour(WHITE)));
+
+    println!("\n Count\n-------\n{}\n", counts);
+}
+
+// Runs every .bf file in the directory, with the bytes of the .in file of the same
+// name as its input if there is one, and checks its output is exactly the same as
+// the .out file. Programs without a .out file are skipped. Exits with 1 if any of
+// them failed.
+fn run_tests(test_dir: &str, options: Options) {
+    let entries = match fs::read_dir(test_dir) {
+        Ok(entries) => entries,
+        Err(error) => throw_exception("FileLoadError", &format!("cannot read the directory \"{}\". ({})", test_dir, error))
+    };
+
+    let mut program_paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "bf"))
+        .collect();
+
+    program_paths.sort();
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+
+    for program_path in program_paths {
+        let name = program_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        let expected_output = match fs::read(program_path.with_extension("out")) {
+            Ok(expected_output) => expected_output,
+            Err(_) => {
+                println!("{}SKIP{} {} - there is no .out file", colour(CYAN), colour(WHITE), name);
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let input_bytes = fs::read(program_path.with_extension("in")).unwrap_or_default();
+
+        let result = fs::read(&program_path)
+            .map_err(|error| format!("cannot read the file. ({})", error))
+            .and_then(|code_bytes| Interpreter::with_options(&decode_code(&code_bytes), options).map_err(|error| error.to_string()))
+            .and_then(|mut interpreter| {
+                interpreter.set_input(Box::new(io::Cursor::new(input_bytes)));
+                interpreter.run().map_err(|error| error.to_string())
+            });
+
+        match result {
+            Ok(output) if output == expected_output => {
+                println!("{}PASS{} {}", colour(GREEN), colour(WHITE), name);
+                passed += 1;
+            }
+
+            Ok(_) => {
+                println!("{}FAIL{} {} - the output was different", colour(RED), colour(WHITE), name);
+                failed += 1;
+            }
+
+            Err(error) => {
+                println!("{}FAIL{} {} - {}", colour(RED), colour(WHITE), name, error);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\n{}{}{} passed, {}{}{} failed, {}{}{} skipped",
+        colour(GREEN), passed, colour(WHITE),
+        colour(RED), failed, colour(WHITE),
+        colour(CYAN), skipped, colour(WHITE)
+    );
+
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+// Runs every file of the dialect in the directory in order of their names, showing
+// the name of each one before it runs and waiting for a keypress after it. A program
+// that errors doesn't stop the rest, but the exit code is 1 if any of them did.
+fn run_playlist(playlist_dir: &str, settings: &RunSettings, options: Options) {
+    let entries = match fs::read_dir(playlist_dir) {
+        Ok(entries) => entries,
+        Err(error) => throw_exception("FileLoadError", &format!("cannot read the directory \"{}\". ({})", playlist_dir, error))
+    };
+
+    let extension = match settings.dialect {
+        Dialect::Brainfuck => "bf",
+        Dialect::Ook => "ook"
+    };
+
+    let mut program_paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|path_extension| path_extension == extension))
+        .collect();
+
+    program_paths.sort();
+
+    let mut failed = 0;
+
+    for (i, program_path) in program_paths.iter().enumerate() {
+        let name = program_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        eprintln!("{}{}{}", colour(CYAN), name, colour(WHITE));
+
+        if !run_playlist_program(&program_path.to_string_lossy(), settings, options) {
+            failed += 1;
+        }
+
+        // Nothing to wait for after the last one, or when
+        // there isn't a terminal to press a key in.
+        if i + 1 < program_paths.len() && Term::stderr().is_term() {
+            eprintln!("{}Press any key for the next program...{}", colour(CYAN), colour(WHITE));
+
+            if Term::stderr().read_key().is_err() {
+                break;
+            }
+
+            eprintln!();
+        }
+    }
+
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+// Runs one program from a playlist, showing any error it stops on rather than
+// exiting, so the programs after it still get to run. Returns false if it errored.
+fn run_playlist_program(program_path: &str, settings: &RunSettings, options: Options) -> bool {
+    // Each program has its own input after a "!".
+    let mut settings = settings.clone();
+
+    let brainfuck_code = match load_code_file(program_path, settings.dialect) {
+        Ok(brainfuck_code) => brainfuck_code,
+        Err(message) => {
+            print_exception("FileLoadError", &message);
+            return false;
+        }
+    };
+
+    let brainfuck_code = match translate_code(brainfuck_code, &mut settings) {
+        Ok(brainfuck_code) => brainfuck_code,
+        Err(error) => {
+            print_error(&error);
+            return false;
+        }
+    };
+
+    if let Err(message) = has_instructions(&brainfuck_code, options, &format!("\"{}\"", program_path)) {
+        print_exception("FileLoadError", &message);
+        return false;
+    }
+
+    match execute_code(&brainfuck_code, &settings, options) {
+        Ok(()) => true,
+        Err(error) => {
+            print_error(&error);
+            false
+        }
+    }
 }
 
-fn display_help() {
-    println!("
-Brainfuck Interpreter
----------------------
+// Reads code a line at a time and runs each line against the same
+// memory, so the pointer and memory blocks carry over between lines.
+fn run_repl(settings: &RunSettings, options: Options) {
+    println!("\n{}Brainfuck REPL{} - memory is kept between lines. Press {}Ctrl-D{} to exit.\n", colour(CYAN), colour(WHITE), colour(CYAN), colour(WHITE));
 
-This is an executable that can run brainfuck files, created by axololly on GitHub.
-        
-To use this, navigate to the directory with the brainfuck file (marked with the .bf
-extension) and run a command that looks like this in terminal:
+    let mut interpreter = Interpreter::with_options("", options)
+        .unwrap_or_else(|error| throw_error(error));
 
-    {0}brainfuck your-file.bf{1}
+    interpreter.set_input(input_source(settings));
+    apply_tape_offset(&mut interpreter, settings);
 
+    let mut output = program_output(settings);
 
-Extra Details
--------------
+    loop {
+        print!("{}>>>{} ", colour(CYAN), colour(WHITE));
+        let _ = io::stdout().flush();
 
-- Brainfuck files can be documented with Rust-style comments that get stripped out before execution.
-  Any special characters that are usually used in brainfuck execution, when commented, are ignored.
+        let mut line = String::new();
 
-- Whitespace is also stripped before execution, meaning whitespace is {2}irrelevant{1} to code execution.
+        // Ctrl-D (end of input) exits the REPL
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                println!();
+                break;
+            }
+            Ok(_) => {}
+        }
 
-- The memory is {0}30,000 blocks{1}, and each block {2}cannot{1} exceed the inclusive range of {0}0-255{1}.
+        if let Err(error) = interpreter.load(&line) {
+            print_error(&error);
+            continue;
+        }
 
-- All 8 instructions are the same.\n", CYAN, WHITE, RED);
-    exit(0)
+        let result = run_code(&mut interpreter, &mut output, &mut None, settings.cell_display);
+        let _ = output.flush();
+
+        if let Err(error) = result {
+            // Finish any output on the line before showing the error.
+            if interpreter.has_output() {
+                println!();
+            }
+
+            print_error(&error);
+            continue;
+        }
+
+        if interpreter.has_output() {
+            println!();
+        }
+
+        if settings.show_memory_after {
+            let ptr = interpreter.pointer_index();
+
+            println!("{}{: >7}{} - {}[{}]{}", colour(CYAN), interpreter.pointer(), colour(WHITE), colour(GREEN), cell_repr(&interpreter, interpreter.memory()[ptr], settings.cell_display), colour(WHITE));
+        }
+    }
+}
+
+// When each of the files was last changed, or None if it can't be found.
+fn modified_times(file_paths: &[&String]) -> Vec<Option<SystemTime>> {
+    file_paths
+        .iter()
+        .map(|file_path| fs::metadata(file_path).and_then(|metadata| metadata.modified()).ok())
+        .collect()
+}
+
+// Runs the interpreter again with the same arguments (apart from --watch) every
+// time one of the files changes, so each run goes exactly like a normal one and
+// its errors don't stop the watching.
+fn run_watch(args: &[String], file_paths: &[&String]) -> ! {
+    let executable = env::current_exe()
+        .unwrap_or_else(|error| throw_exception("FileLoadError", &format!("cannot find the interpreter to run again. ({})", error)));
+
+    let run_args: Vec<&String> = args.iter().filter(|arg| *arg != "--watch").collect();
+
+    loop {
+        let last_modified = modified_times(file_paths);
+
+        let _ = Term::stdout().clear_screen();
+
+        // Worked out from the system clock, since the time zone isn't known.
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let (hours, minutes, seconds) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+
+        println!("{}Running at {:02}:{:02}:{:02} UTC{}", colour(CYAN), hours, minutes, seconds, colour(WHITE));
+
+        if let Err(error) = Command::new(&executable).args(&run_args).status() {
+            throw_exception("FileLoadError", &format!("cannot run the interpreter again. ({})", error));
+        }
+
+        println!("{}Waiting for the files to change...{}", colour(CYAN), colour(WHITE));
+
+        while modified_times(file_paths) == last_modified {
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+// Reads the code from a file, which has to have the extension for the dialect
+// and some code in it.
+fn read_code_file(file_path: &str, dialect: Dialect) -> String {
+    load_code_file(file_path, dialect).unwrap_or_else(|message| throw_exception("FileLoadError", &message))
+}
+
+// The same as `read_code_file`, but giving back the message for why the file
+// couldn't be read, for running several files where one failing isn't the end.
+fn load_code_file(file_path: &str, dialect: Dialect) -> Result<String, String> {
+    let extension = match dialect {
+        Dialect::Brainfuck => ".bf",
+        Dialect::Ook => ".ook"
+    };
+
+    // If file is not a brainfuck file
+    if !file_path.ends_with(extension) {
+        return Err(format!("cannot run code from a file that does not have the extension {}{}", colour(CYAN), extension));
+    }
+
+    let file_bytes = match fs::read(file_path) {
+        Ok(file_bytes) => file_bytes,
+        Err(error) => return Err(format!("cannot read the file \"{}\". ({})", file_path, error))
+    };
+
+    let brainfuck_code = decode_code(&file_bytes);
+
+    // If there's no code to execute
+    if brainfuck_code.is_empty() {
+        return Err(format!("\"{}\" does not contain any code to execute.", file_path));
+    }
+
+    Ok(brainfuck_code)
 }
 
-fn sanitise_code(code: &mut str) -> String {
-    let binding = Regex::new(r"\/\/.+")
-        .unwrap()
-        .replace(code, "");
+// Turn the code into brainfuck to run, splitting off the input after a "!"
+// first with --bang-input, as it could have anything in it.
+fn translate_code(code: String, settings: &mut RunSettings) -> Result<String, BrainfuckError> {
+    let code = if settings.bang_input {
+        let (code, input_text) = split_bang_input(&code)?;
+
+        settings.input_text = Some(input_text);
+        code
+    }
+    else {
+        code
+    };
+
+    let code = match settings.dialect {
+        Dialect::Brainfuck => code,
+        Dialect::Ook => translate_ook(&code)?
+    };
 
-    let new_code = binding.as_ref();
-    
-    let binding = Regex::new(r"\n|\r| |\t")
-        .unwrap()
-        .replace_all(new_code, "");
+    Ok(match settings.mapping {
+        Some(mapping) => translate_mapping(&code, mapping),
+        None => code
+    })
+}
 
-    let new_code_2 = binding.as_ref();
-    
-    let binding = Regex::new(r"(\/\*)|(\*\/)")
-        .unwrap()
-        .replace_all(new_code_2, "");
-    
-    let new_code_3 = binding.as_ref();
-    
-    let stray_comment_pos = new_code_3.find("/*");
-    
-    if let Some(stray_comment_pos) = stray_comment_pos {
-        throw_exception_with_pos("SyntaxError", stray_comment_pos as i32, "cannot import code with unterminated multi-line comments. (\"/*\" was found in the code.)");
+// A file with only comments and whitespace in it isn't empty, but there's
+// nothing to run either, which most likely means the wrong file was given.
+// Anything that doesn't sanitise gets its error once it's run instead.
+fn check_has_instructions(code: &str, options: Options, source: &str) {
+    if let Err(message) = has_instructions(code, options, source) {
+        throw_exception("FileLoadError", &message);
     }
+}
+
+fn has_instructions(code: &str, options: Options, source: &str) -> Result<(), String> {
+    let brainfuck_code = match sanitise_code(code) {
+        Ok(brainfuck_code) => brainfuck_code,
+        Err(_) => return Ok(())
+    };
 
-    let stray_comment_pos = new_code_3.find("*/");
+    let has_instructions = brainfuck_code.code
+        .iter()
+        .any(|c| options.strict || INSTRUCTIONS.contains(c) || (*c == '#' && options.debug_char));
 
-    if let Some(stray_comment_pos) = stray_comment_pos {
-        throw_exception_with_pos("SyntaxError", stray_comment_pos as i32, "cannot import code with stray comment characters. (\"*/\" was found in the code.)");
+    if !has_instructions {
+        return Err(format!("{} only contains comments and whitespace, so there are no instructions to run.", source));
     }
 
-    let while_loop_starts = new_code_3
-        .bytes()
-        .filter(|c| *c == b'[')
-        .count();
+    Ok(())
+}
+
+fn main() {
+    // Note that args contains the .exe name, so
+    // each of the key arguments is 1-indexed 
+    // instead of 0-indexed.
+    let args: Vec<String> = env::args().collect();
+
+    // Checked before anything else so the help and
+    // argument errors are affected too.
+    let no_color_env = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
 
-    let while_loop_ends = new_code_3
-        .bytes()
-        .filter(|c| *c == b']')
-        .count();
+    // Asking whether colours are supported also turns on ANSI colour
+    // processing for Windows consoles. If that fails (or the output isn't
+    // a terminal), the escape codes would show up as text, so leave them out.
+    // Everything coloured apart from the help goes to stderr, so that's the one checked.
+    let colours_supported = Term::stderr().features().colors_supported();
 
-    if while_loop_starts > while_loop_ends {
-        let i = code.find("[");
+    // Whichever of --color and --no-color comes last is used.
+    let mut colour_mode = "auto";
 
-        if let Some(i) = i {
-            throw_exception_with_pos("SyntaxError", i as i32, "cannot import code with unterminated while loops. (Unmatched \"[\" was found in the code.)");
+    for (i, arg) in args.iter().enumerate() {
+        match arg.as_str() {
+            "--no-color" => colour_mode = "never",
+            "--color" => colour_mode = args.get(i + 1).map_or("auto", |mode| mode.as_str()),
+            _ => {}
         }
     }
 
-    if while_loop_starts < while_loop_ends {
-        let i = code.rfind("]");
+    let colours_enabled = match colour_mode {
+        "always" => true,
+        "never" => false,
+        _ => !no_color_env && colours_supported
+    };
+
+    COLOURS_ENABLED.store(colours_enabled, Ordering::Relaxed);
+
+    if args.windows(2).any(|pair| pair[0] == "--error-format" && pair[1] == "json") {
+        JSON_ERRORS.store(true, Ordering::Relaxed);
+    }
+
+    // Run the .exe with no arguments
+    if args.len() == 1 {
+        display_help();
+    }
+
+    // Run the .exe with the help or version argument, wherever it is
+    if args[1..].iter().any(|arg| arg == "-h" || arg == "--help") {
+        display_help();
+    }
+
+    if args[1..].iter().any(|arg| arg == "-V" || arg == "--version") {
+        println!("brainfuck {}", env!("CARGO_PKG_VERSION"));
+        exit(0);
+    }
+
+    let mut settings = RunSettings::default();
+    let mut repl = false;
+    let mut options = Options::default();
+    let mut file_paths: Vec<&String> = Vec::new();
 
-        if let Some(i) = i {
-            throw_exception_with_pos("SyntaxError", i as i32, "cannot import code with trailing while loop characters. (Unmatched \"]\" was found in the code.)");
+    // The preset is set before any other options, so they can change it wherever they are.
+    if let Some(pair) = args.windows(2).rfind(|pair| pair[0] == "--compat") {
+        if !apply_compat_preset(&pair[1], &mut options) {
+            throw_exception("ArgumentError", &format!("expected 'classic', 'beef' or 'bfdev' for --compat - received \"{}\".", pair[1]));
         }
     }
 
-    new_code_3.to_string()
-}
+    let mut inline_code: Option<&String> = None;
+
+    let mut remaining_args = args[1..].iter().peekable();
+
+    while let Some(arg) = remaining_args.next() {
+        match arg.as_str() {
+            "--allow-debug-char" => options.debug_char = true,
+
+            "--bang-input" => settings.bang_input = true,
+
+            "--bench" => {
+                // The number of runs is optional, so only take the next argument if it's a number.
+                let iterations = match remaining_args.peek().and_then(|iterations| iterations.parse::<usize>().ok()) {
+                    Some(iterations) => {
+                        remaining_args.next();
+                        iterations
+                    }
+                    None => DEFAULT_BENCH_ITERATIONS
+                };
+
+                if iterations == 0 {
+                    throw_exception("ArgumentError", "expected a positive integer for --bench - received \"0\".");
+                }
+
+                settings.bench_iterations = Some(iterations);
+            }
+
+            "--bidirectional" => options.bidirectional = true,
+
+            "--break" => {
+                let positions = match remaining_args.next() {
+                    Some(positions) => positions,
+                    None => throw_exception("ArgumentError", "expected a list of positions after --break, like '4,12'.")
+                };
+
+                for position in positions.split(',') {
+                    match position.trim().parse::<usize>() {
+                        Ok(position) => settings.breakpoints.push(position),
+                        Err(_) => throw_exception("ArgumentError", &format!("expected a position for --break - received \"{}\".", position))
+                    }
+                }
+            }
+
+            "-c" | "--code" => {
+                match remaining_args.next() {
+                    Some(code) => inline_code = Some(code),
+                    None => throw_exception("ArgumentError", "expected some code to run after -c.")
+                }
+            }
+
+            "--count-only" => settings.count_only = true,
+
+            "-d" | "--debug" => settings.show_memory_after = true,
+
+            "--dialect" => {
+                settings.dialect = match remaining_args.next().map(|dialect| dialect.as_str()) {
+                    Some("brainfuck") => Dialect::Brainfuck,
+                    Some("ook") => Dialect::Ook,
+                    Some(dialect) => throw_exception("ArgumentError", &format!("expected 'brainfuck' or 'ook' for --dialect - received \"{}\".", dialect)),
+                    None => throw_exception("ArgumentError", "expected 'brainfuck' or 'ook' after --dialect.")
+                };
+            }
+
+            "--dump-memory" => {
+                match remaining_args.next() {
+                    Some(dump_path) => settings.memory_dump_path = Some(dump_path.clone()),
+                    None => throw_exception("ArgumentError", "expected a file path after --dump-memory.")
+                }
+            }
+
+            "--dump-ir" => settings.dump_ir = true,
+
+            "--format" => settings.format_width = Some(settings.format_width.unwrap_or(DEFAULT_FORMAT_WIDTH)),
 
-fn execute_code(code: &mut str, show_memory_after: bool) -> () {
-    println!("");
-    
-    let brainfuck_code = sanitise_code(code);
+            "--format-width" => settings.format_width = Some(parse_positive_arg("--format-width", remaining_args.next())),
 
-    let mut code_index: usize = 0;
-    let mut while_loop_start_indexes: Vec<i32> = Vec::new();
+            "--frame-rate" => {
+                let frame_rate = parse_positive_arg("--frame-rate", remaining_args.next());
 
-    let mut has_console_output = false;
+                // The time between frames is a second divided by it, which has to fit in 32 bits.
+                settings.frame_rate = match u32::try_from(frame_rate) {
+                    Ok(frame_rate) => Some(frame_rate),
+                    Err(_) => throw_exception("ArgumentError", &format!("expected at most {} steps a second for --frame-rate - received {}.", u32::MAX, frame_rate))
+                };
+            }
+
+            "--generate" => {
+                match remaining_args.next() {
+                    Some(text) => settings.generate_text = Some(text.clone()),
+                    None => throw_exception("ArgumentError", "expected some text after --generate.")
+                }
+            }
 
-    let mut memory: [i32; 30_000] = [0; 30_000];
-    let mut ptr = 0;
-    let mut furthest_ptr = 0;
+            "--heatmap" => {
+                options.count_accesses = true;
+                settings.show_memory_after = true;
+            }
 
-    while code_index < brainfuck_code.len() {
-        let current = brainfuck_code.as_bytes()[code_index] as char;
+            "--hello" => settings.hello = true,
 
-        match current {
-            '>' => {
-                // Gone out of rightward bounds
-                if ptr == 29_999 {
-                    throw_exception("OutOfBoundsError", "cannot move pointer outside of rightward bounds.");
+            "--input" => {
+                match remaining_args.next() {
+                    Some(input_path) => settings.input_path = Some(input_path.clone()),
+                    None => throw_exception("ArgumentError", "expected a file path after --input.")
                 }
+            }
 
-                ptr += 1;
-                
-                // Keep record of furthest pointer for
-                // when we print the memory cells.
-                if ptr > furthest_ptr {
-                    furthest_ptr = ptr;
+            "--lint" => settings.lint = true,
+
+            "--load-state" => {
+                match remaining_args.next() {
+                    Some(state_path) => settings.load_state_path = Some(state_path.clone()),
+                    None => throw_exception("ArgumentError", "expected a file path after --load-state.")
                 }
             }
 
-            '<' => {
-                // Gone out of leftward bounds
-                if ptr == 0 {
-                    throw_exception("OutOfBoundsError", "cannot move pointer outside of leftward bounds.");
+            "--map" => {
+                let mapping = match remaining_args.next() {
+                    Some(mapping) => mapping,
+                    None => throw_exception("ArgumentError", "expected the characters to use for \"+-<>[].,\" after --map.")
+                };
+
+                let mapping_chars: Vec<char> = mapping.chars().collect();
+                let is_unique = mapping_chars.iter().enumerate().all(|(i, c)| !mapping_chars[..i].contains(c));
+
+                match <[char; 8]>::try_from(mapping_chars.as_slice()) {
+                    Ok(mapping_chars) if is_unique => settings.mapping = Some(mapping_chars),
+                    _ => throw_exception("ArgumentError", &format!("expected 8 different characters for --map, one for each of \"+-<>[].,\" - received \"{}\".", mapping))
                 }
+            }
+
+            "--max-output" => options.max_output = Some(parse_positive_arg("--max-output", remaining_args.next())),
+
+            "--max-steps" => options.max_steps = Some(parse_positive_arg("--max-steps", remaining_args.next())),
+
+            "--memory" => options.memory_size = parse_positive_arg("--memory", remaining_args.next()),
+
+            "--memory-window" => settings.memory_window_size = Some(parse_positive_arg("--memory-window", remaining_args.next())),
+
+            "--minify" => settings.minify = true,
+
+            // Already handled before parsing the other arguments.
+            "-h" | "--help" | "--no-color" | "-V" | "--version" => {}
 
-                ptr -= 1;
+            "--numeric" => options.numeric_output = true,
+
+            "-o" | "--output" => {
+                match remaining_args.next() {
+                    Some(output_path) => settings.output_path = Some(output_path.clone()),
+                    None => throw_exception("ArgumentError", "expected a file path after -o.")
+                }
             }
 
-            '+' => {
-                if memory[ptr] == 255 {
-                    throw_exception("OverflowError", "cannot increment memory block past integer limit of 255.");
+            "--playlist" => {
+                match remaining_args.next() {
+                    Some(playlist_dir) => settings.playlist_dir = Some(playlist_dir.clone()),
+                    None => throw_exception("ArgumentError", "expected a directory after --playlist.")
                 }
+            }
+
+            "--profile" => options.profile = true,
 
-                memory[ptr] += 1;
+            "-q" | "--quiet" => settings.quiet = true,
+
+            "--random-input" => settings.random_input = true,
+
+            "--repl" => repl = true,
+
+            "--reverse-debug" => {
+                options.record_history = true;
+                settings.step_through = true;
             }
 
-            '-' => {
-                if memory[ptr] == 0 {
-                    throw_exception("SubZeroError", "cannot decrement memory block below 0.");
+            "--save-state" => {
+                match remaining_args.next() {
+                    Some(state_path) => settings.save_state_path = Some(state_path.clone()),
+                    None => throw_exception("ArgumentError", "expected a file path after --save-state.")
                 }
+            }
 
-                memory[ptr] -= 1;
+            "--seed" => {
+                settings.seed = match remaining_args.next() {
+                    Some(seed) => match seed.parse::<u64>() {
+                        Ok(seed) => Some(seed),
+                        Err(_) => throw_exception("ArgumentError", &format!("expected a whole number for --seed - received \"{}\".", seed))
+                    },
+                    None => throw_exception("ArgumentError", "expected a whole number after --seed.")
+                };
             }
 
-            '[' => {
-                while_loop_start_indexes.push(code_index as i32);
+            "--show-zeros" => settings.show_zeros = true,
+
+            "--signed-cells" => options.signed_cells = true,
+
+            "--stats" => settings.show_stats = true,
+
+            "--stdin" => {
+                match remaining_args.next() {
+                    Some(input_text) => settings.input_text = Some(input_text.clone()),
+                    None => throw_exception("ArgumentError", "expected some text after --stdin.")
+                }
+            }
+
+            "--step" => settings.step_through = true,
+
+            "--strict" => options.strict = true,
+
+            // Block 0 is allowed as well, and how far along it can be is checked once the memory size is known.
+            "--tape-offset" => {
+                settings.tape_offset = match remaining_args.next() {
+                    Some(block) => match block.parse::<usize>() {
+                        Ok(block) => Some(block),
+                        Err(_) => throw_exception("ArgumentError", &format!("expected a memory block for --tape-offset - received \"{}\".", block))
+                    },
+                    None => throw_exception("ArgumentError", "expected a memory block after --tape-offset.")
+                };
             }
 
-            ']' => {
-                // Send the code pointer back to the start of the while loop
-                // if the cell the pointer lands on is above 0.
-                if memory[ptr] > 0 {
-                    code_index = *while_loop_start_indexes
-                        .last()
-                        .expect(&*format!("{}Fatal Error: the while loop last indexes array did not contain any indexes.{}", RED, WHITE)) as usize;
+            "--test" => {
+                match remaining_args.next() {
+                    Some(test_dir) => settings.test_dir = Some(test_dir.clone()),
+                    None => throw_exception("ArgumentError", "expected a directory after --test.")
                 }
+            }
 
-                // Otherwise, remove the latest index as we have gone up a
-                // level, in terms of nested while loops.
-                else {
-                    while_loop_start_indexes.pop();
+            "--trace" => {
+                match remaining_args.next() {
+                    Some(trace_path) => settings.trace_path = Some(trace_path.clone()),
+                    None => throw_exception("ArgumentError", "expected a file path after --trace.")
                 }
             }
 
-            '.' => {
-                println!("{}", char::from_u32(memory[ptr] as u32).unwrap());
+            "--unsafe-no-checks" => options.unchecked = true,
+
+            "--visualize" => settings.frame_rate = Some(settings.frame_rate.unwrap_or(DEFAULT_FRAME_RATE)),
+
+            "--watch" => settings.watch = true,
+
+            "--wrap-cells" => {
+                options.wrap_increments = true;
+                options.wrap_decrements = true;
+            }
+
+            "--wrap-tape" => options.wrap_tape = true,
+
+            "--cell-display" => {
+                settings.cell_display = match remaining_args.next().map(|cell_display| cell_display.as_str()) {
+                    Some("char") => CellDisplay::Char,
+                    Some("dec") => CellDisplay::Dec,
+                    Some("hex") => CellDisplay::Hex,
+                    Some(cell_display) => throw_exception("ArgumentError", &format!("expected 'char', 'dec' or 'hex' for --cell-display - received \"{}\".", cell_display)),
+                    None => throw_exception("ArgumentError", "expected 'char', 'dec' or 'hex' after --cell-display.")
+                };
+            }
+
+            "--cell-size" => {
+                options.cell_size = match remaining_args.next().map(|bits| bits.as_str()) {
+                    Some("8") => CellSize::Eight,
+                    Some("16") => CellSize::Sixteen,
+                    Some("32") => CellSize::ThirtyTwo,
+                    Some(bits) => throw_exception("ArgumentError", &format!("expected '8', '16' or '32' for --cell-size - received \"{}\".", bits)),
+                    None => throw_exception("ArgumentError", "expected '8', '16' or '32' after --cell-size.")
+                };
+            }
+
+            "--emit" => {
+                settings.emit_target = match remaining_args.next().map(|language| language.as_str()) {
+                    Some("c") => Some(EmitTarget::C),
+                    Some("rust") => Some(EmitTarget::Rust),
+                    Some("wat") => Some(EmitTarget::Wat),
+                    Some(language) => throw_exception("ArgumentError", &format!("expected 'c', 'rust' or 'wat' for --emit - received \"{}\".", language)),
+                    None => throw_exception("ArgumentError", "expected 'c', 'rust' or 'wat' after --emit.")
+                };
+            }
+
+            "--wrap" => {
+                (options.wrap_increments, options.wrap_decrements) = match remaining_args.next().map(|mode| mode.as_str()) {
+                    Some("none") => (false, false),
+                    Some("inc") => (true, false),
+                    Some("dec") => (false, true),
+                    Some("both") => (true, true),
+                    Some(mode) => throw_exception("ArgumentError", &format!("expected 'none', 'inc', 'dec' or 'both' for --wrap - received \"{}\".", mode)),
+                    None => throw_exception("ArgumentError", "expected 'none', 'inc', 'dec' or 'both' after --wrap.")
+                };
+            }
+
+            "--eof" => {
+                options.eof_mode = match remaining_args.next().map(|mode| mode.as_str()) {
+                    Some("unchanged") => EofMode::Unchanged,
+                    Some("zero") => EofMode::Zero,
+                    Some("max") => EofMode::Max,
+                    Some(mode) => throw_exception("ArgumentError", &format!("expected 'unchanged', 'zero' or 'max' for --eof - received \"{}\".", mode)),
+                    None => throw_exception("ArgumentError", "expected 'unchanged', 'zero' or 'max' after --eof.")
+                };
+            }
 
-                has_console_output = true;
+            // Already looked at before anything else, but the format still has to be checked.
+            "--error-format" => {
+                match remaining_args.next().map(|format| format.as_str()) {
+                    Some("human") | Some("json") => {}
+                    Some(format) => throw_exception("ArgumentError", &format!("expected 'human' or 'json' for --error-format - received \"{}\".", format)),
+                    None => throw_exception("ArgumentError", "expected 'human' or 'json' after --error-format.")
+                }
             }
 
-            ',' => {
-                let input_char = Term::stdout().read_char().unwrap();
+            // Already looked at before anything else, but the mode still has to be checked.
+            "--color" => {
+                match remaining_args.next().map(|mode| mode.as_str()) {
+                    Some("auto") | Some("always") | Some("never") => {}
+                    Some(mode) => throw_exception("ArgumentError", &format!("expected 'auto', 'always' or 'never' for --color - received \"{}\".", mode)),
+                    None => throw_exception("ArgumentError", "expected 'auto', 'always' or 'never' after --color.")
+                }
+            }
 
-                if input_char as i32 > 255 {
-                    throw_exception_with_pos("OverflowError", code_index as i32, "inputted character exceeds value of 255.");
+            // Already set before anything else, but the preset still has to be checked.
+            "--compat" => {
+                match remaining_args.next() {
+                    Some(preset) if apply_compat_preset(preset, &mut Options::default()) => {}
+                    Some(preset) => throw_exception("ArgumentError", &format!("expected 'classic', 'beef' or 'bfdev' for --compat - received \"{}\".", preset)),
+                    None => throw_exception("ArgumentError", "expected 'classic', 'beef' or 'bfdev' after --compat.")
                 }
+            }
 
-                memory[ptr] = input_char as i32;
+            "--encoding" => {
+                settings.encoding = match remaining_args.next().map(|encoding| encoding.as_str()) {
+                    Some("ascii") => Encoding::Ascii,
+                    Some("latin1") => Encoding::Latin1,
+                    Some("utf8") => Encoding::Utf8,
+                    Some(encoding) => throw_exception("ArgumentError", &format!("expected 'ascii', 'latin1' or 'utf8' for --encoding - received \"{}\".", encoding)),
+                    None => throw_exception("ArgumentError", "expected 'ascii', 'latin1' or 'utf8' after --encoding.")
+                };
             }
 
-            _ => {
-                throw_exception_with_pos("SyntaxError", code_index as i32, &*format!("unrecognised character '{}' found in code.", brainfuck_code.as_bytes()[code_index] as char));
+            "--output-newline" => {
+                settings.output_newline = match remaining_args.next().map(|mode| mode.as_str()) {
+                    Some("lf") => OutputNewline::Lf,
+                    Some("crlf") => OutputNewline::Crlf,
+                    Some("strip-cr") => OutputNewline::StripCr,
+                    Some(mode) => throw_exception("ArgumentError", &format!("expected 'lf', 'crlf' or 'strip-cr' for --output-newline - received \"{}\".", mode)),
+                    None => throw_exception("ArgumentError", "expected 'lf', 'crlf' or 'strip-cr' after --output-newline.")
+                };
             }
+
+            _ if arg.starts_with('-') && arg != "-" => {
+                throw_exception("ArgumentError", &format!("unrecognised flag \"{}\".", arg));
+            }
+
+            _ => file_paths.push(arg)
         }
+    }
 
-        code_index += 1;
+    if settings.input_path.is_some() && settings.input_text.is_some() {
+        throw_exception("ArgumentError", "cannot read input from --input and --stdin at the same time.");
     }
 
-    if !has_console_output {
-        println!("{}No output provided.{}", RED, WHITE);
+    if options.bidirectional && options.unchecked {
+        throw_exception("ArgumentError", "cannot grow the memory in both directions without checking the pointer.");
     }
 
-    println!();
+    if options.bidirectional && options.wrap_tape {
+        throw_exception("ArgumentError", "cannot wrap the pointer around the memory when it can grow in both directions.");
+    }
 
-    if show_memory_after {
-        let mut locations_to_values = String::new();
+    if settings.random_input && (settings.input_path.is_some() || settings.input_text.is_some() || settings.bang_input) {
+        throw_exception("ArgumentError", "cannot read random input along with other input.");
+    }
 
-        for i in 0..(furthest_ptr + 1) {
-            if memory[i] > 0 {
-                let mem_block_repr = &*memory[i].to_string();
-                let mem_block_pos = &*i.to_string();
-                
-                locations_to_values.push_str(
-                    &*format!(
-                        "{}{: >7}{} - {}[{}]{}",
-                        CYAN,
-                        mem_block_pos,
-                        WHITE,
-                        GREEN,
-                        mem_block_repr,
-                        WHITE
-                    )
-                );
-                locations_to_values.push_str("\n");
-            }
+    if settings.seed.is_some() && !settings.random_input {
+        throw_exception("ArgumentError", "--seed only works with --random-input.");
+    }
+
+    // The debugger pauses on single instructions, the trace has a line for
+    // each one and states can be saved in between any two of them, so runs
+    // of them can't be collapsed into a single step.
+    let single_steps = settings.step_through
+        || !settings.breakpoints.is_empty()
+        || settings.trace_path.is_some()
+        || settings.load_state_path.is_some()
+        || settings.save_state_path.is_some()
+        || settings.frame_rate.is_some();
+
+    if single_steps {
+        options.optimise = false;
+    }
+
+    if settings.hello {
+        run_hello(options);
+        return;
+    }
+
+    if let Some(text) = &settings.generate_text {
+        if output_target(&settings).write_all(generate_code(text).as_bytes()).is_err() {
+            throw_exception("OutputError", "could not write the generated code to the output.");
         }
 
-        locations_to_values.push_str(&*format!("\n    ptr => {1}{2}{0}", WHITE, CYAN, ptr));
+        return;
+    }
 
-        println!("\n Memory Breakdown\n------------------\n{}", locations_to_values);
+    if let Some(test_dir) = &settings.test_dir {
+        run_tests(test_dir, options);
+        return;
     }
-}
 
-fn main() {
-    // Note that args contains the .exe name, so
-    // each of the key arguments is 1-indexed 
-    // instead of 0-indexed.
-    let args: Vec<String> = env::args().collect();
+    if settings.bang_input {
+        if settings.dialect == Dialect::Ook {
+            throw_exception("ArgumentError", "cannot use --bang-input with Ook! code, which has \"!\" in its instructions.");
+        }
 
-    // Run the .exe with no arguments
-    if args.len() == 1 {
-        display_help();
+        if settings.input_path.is_some() || settings.input_text.is_some() {
+            throw_exception("ArgumentError", "cannot read input from after a \"!\" with --input or --stdin as well.");
+        }
     }
 
-    // Run the .exe with the help argument
-    if args[1] == "-h" || args[1] == "--help" {
-        display_help();
+    if let Some(playlist_dir) = &settings.playlist_dir {
+        if !file_paths.is_empty() || inline_code.is_some() {
+            throw_exception("ArgumentError", "cannot run a file or code from -c with --playlist.");
+        }
+
+        run_playlist(playlist_dir, &settings, options);
+        return;
+    }
+
+    if settings.watch {
+        if file_paths.is_empty() || file_paths.iter().any(|file_path| *file_path == "-") {
+            throw_exception("ArgumentError", "--watch needs the code to be run from files.");
+        }
+
+        run_watch(&args[1..], &file_paths);
     }
 
-    /*
-    The valid arguments (with debug flag) would be:
-    
-       brainfuck.exe path-to-file.bf --debug
-    
-    Which is 3 total arguments.
-    */
-    if args.len() > 3 {
-        throw_exception("ArgumentError", &*format!("too many arguments were provided.\n\n{}If this is meant to be a file path, wrap it in \"quotation marks\"", CYAN));
+    if repl {
+        if !file_paths.is_empty() || inline_code.is_some() {
+            throw_exception("ArgumentError", "cannot run a file or code from -c in the REPL.");
+        }
+
+        run_repl(&settings, options);
+        return;
     }
 
-    let mut show_memory_output = false;
+    let brainfuck_code = match (file_paths.as_slice(), inline_code) {
+        ([], Some(code)) => code.clone(),
+
+        // A file path of "-" reads the code from stdin instead,
+        // so it can be piped in from another program.
+        ([file_path], None) if *file_path == "-" => {
+            let mut code_bytes = Vec::new();
 
-    if args.len() == 3 {
-        if args[2] == "-d" || args[2] == "--debug" {
-            show_memory_output = true;
+            if let Err(error) = io::stdin().read_to_end(&mut code_bytes) {
+                throw_exception("FileLoadError", &format!("cannot read code from stdin. ({})", error));
+            }
+
+            decode_code(&code_bytes)
         }
-        else {
-            throw_exception("ArgumentError", &*format!("expected '-d' or '--debug' - received \"{}\".", args[2]));
+
+        ([], None) => throw_exception("ArgumentError", "no file path was provided."),
+
+        (file_paths, None) if file_paths.iter().any(|file_path| *file_path == "-") => {
+            throw_exception("ArgumentError", "cannot read code from stdin and from files at the same time.")
+        }
+
+        // Several files get run as one, so brackets can be opened in one
+        // file and closed in another.
+        (file_paths, None) => {
+            let mut file_codes: Vec<String> = file_paths
+                .iter()
+                .map(|file_path| read_code_file(file_path, settings.dialect))
+                .collect();
+
+            // Only a "#!" at the very top of the code is already a comment, so the
+            // ones at the top of the other files are taken out, leaving the line empty.
+            for file_code in file_codes.iter_mut().skip(1) {
+                if file_code.starts_with("#!") {
+                    file_code.drain(..file_code.find('\n').unwrap_or(file_code.len()));
+                }
+            }
+
+            if file_paths.len() > 1 {
+                let mut first_line = 1;
+                let mut source_files = Vec::new();
+
+                for (file_path, file_code) in file_paths.iter().zip(&file_codes) {
+                    source_files.push((file_path.to_string(), first_line));
+                    first_line += file_code.matches('\n').count() + 1;
+                }
+
+                let _ = SOURCE_FILES.set(source_files);
+            }
+
+            file_codes.join("\n")
         }
+
+        ([_, ..], Some(_)) => throw_exception("ArgumentError", "cannot run a file and code from -c at the same time.")
+    };
+
+    let brainfuck_code = translate_code(brainfuck_code, &mut settings)
+        .unwrap_or_else(|error| throw_error(error));
+
+    if settings.lint {
+        show_lint_warnings(&brainfuck_code);
     }
-    
-    let file_path = &args[1];
 
-    // If file is not a brainfuck file
-    if !file_path.ends_with(".bf") {
-        throw_exception("FileLoadError", &*format!("cannot run code from a file that does not have the extension {}.bf", CYAN));
+    if settings.minify {
+        // Outside of strict mode, anything that isn't an instruction is a comment too.
+        let minified_code: String = sanitise_code(&brainfuck_code)
+            .unwrap_or_else(|error| throw_error(error))
+            .code
+            .into_iter()
+            .filter(|c| options.strict || INSTRUCTIONS.contains(c) || (*c == '#' && options.debug_char))
+            .collect();
+
+        if writeln!(output_target(&settings), "{}", minified_code).is_err() {
+            throw_exception("OutputError", "could not write the minified code to the output.");
+        }
+
+        return;
     }
 
-    let mut brainfuck_code = fs::read_to_string(file_path).unwrap();
+    if let Some(format_width) = settings.format_width {
+        let formatted_code = format_code(&brainfuck_code, format_width)
+            .unwrap_or_else(|error| throw_error(error));
 
-    // If there's no code to execute
-    if brainfuck_code.len() == 0 {
-        throw_exception("FileLoadError", "file does not contain any code to execute.");
+        if output_target(&settings).write_all(formatted_code.as_bytes()).is_err() {
+            throw_exception("OutputError", "could not write the formatted code to the output.");
+        }
+
+        return;
+    }
+
+    if settings.dump_ir {
+        show_ir(&brainfuck_code, &settings, options);
+        return;
+    }
+
+    if let Some(emit_target) = settings.emit_target {
+        let program = emit_code(&brainfuck_code, emit_target, options)
+            .unwrap_or_else(|error| throw_error(error));
+
+        if output_target(&settings).write_all(program.as_bytes()).is_err() {
+            throw_exception("OutputError", "could not write the translated code to the output.");
+        }
+
+        return;
+    }
+
+    match file_paths.as_slice() {
+        [] => {}
+        [file_path] if *file_path == "-" => {}
+        [file_path] => check_has_instructions(&brainfuck_code, options, &format!("\"{}\"", file_path)),
+        _ => check_has_instructions(&brainfuck_code, options, "the files")
+    }
+
+    if let Some(iterations) = settings.bench_iterations {
+        run_benchmark(&brainfuck_code, &settings, options, iterations);
+        return;
+    }
+
+    if settings.count_only {
+        run_count_only(&brainfuck_code, &settings, options);
+        return;
     }
 
-    execute_code(&mut brainfuck_code, show_memory_output);
+    if let Err(error) = execute_code(&brainfuck_code, &settings, options) {
+        throw_error(error);
+    }
 }
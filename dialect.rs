@@ -0,0 +1,249 @@
+use crate::error::{BrainfuckError, Position};
+
+// Translate Ook! code into brainfuck. Each instruction is a pair of "Ook."
+// "Ook?" or "Ook!" words, and the translated code keeps the same lines as
+// the original, so errors still point at the right line.
+pub fn translate_ook(code: &str) -> Result<String, BrainfuckError> {
+    let mut translated = String::new();
+    let mut translated_line = 1;
+    let mut instruction_count = 0;
+
+    // The first word of the pair being read, if there is one.
+    let mut first_word: Option<(char, Position)> = None;
+
+    for (i, line) in code.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut column = 0;
+
+        while column < chars.len() {
+            if chars[column].is_whitespace() {
+                column += 1;
+                continue;
+            }
+
+            let start = column;
+
+            while column < chars.len() && !chars[column].is_whitespace() {
+                column += 1;
+            }
+
+            let word: String = chars[start..column].iter().collect();
+            let pos = Position { index: instruction_count, line: i + 1, column: start + 1 };
+
+            let punctuation = match word.as_str() {
+                "Ook." => '.',
+                "Ook?" => '?',
+                "Ook!" => '!',
+                _ => return Err(BrainfuckError::UnrecognisedOok { pos, token: word })
+            };
+
+            let (first_punctuation, first_pos) = match first_word.take() {
+                Some(first_word) => first_word,
+                None => {
+                    first_word = Some((punctuation, pos));
+                    continue;
+                }
+            };
+
+            let instruction = match (first_punctuation, punctuation) {
+                ('.', '?') => '>',
+                ('?', '.') => '<',
+                ('.', '.') => '+',
+                ('!', '!') => '-',
+                ('!', '.') => '.',
+                ('.', '!') => ',',
+                ('!', '?') => '[',
+                ('?', '!') => ']',
+
+                _ => {
+                    let token = format!("Ook{} Ook{}", first_punctuation, punctuation);
+
+                    return Err(BrainfuckError::UnrecognisedOok { pos: first_pos, token });
+                }
+            };
+
+            while translated_line < first_pos.line {
+                translated.push('\n');
+                translated_line += 1;
+            }
+
+            translated.push(instruction);
+            instruction_count += 1;
+        }
+    }
+
+    if let Some((_, pos)) = first_word {
+        return Err(BrainfuckError::UnpairedOok { pos });
+    }
+
+    Ok(translated)
+}
+
+// Split code and the input for it that have been put together with a "!" in
+// between, where anything before the first "!" is the code and anything after it
+// is the input. A "!" can be escaped with a backslash, so "\!" in the input is
+// a "!" of its own, and any other "!" after the first one is an error. Without
+// a "!", all of it is the code and there is no input. A "!" in a "//" or "/* */"
+// comment in the code is part of the comment, not the start of the input.
+pub fn split_bang_input(code: &str) -> Result<(String, String), BrainfuckError> {
+    let mut program = String::new();
+    let mut input = String::new();
+    let mut found_bang = false;
+
+    let mut line = 1;
+    let mut column = 1;
+    let mut chars = code.chars().enumerate().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let pos = Position { index: i, line, column };
+
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        }
+        else {
+            column += 1;
+        }
+
+        match c {
+            '\\' if chars.peek().is_some_and(|(_, next)| *next == '!') => {
+                chars.next();
+                column += 1;
+
+                if found_bang {
+                    input.push('!');
+                }
+                else {
+                    program.push_str("\\!");
+                }
+            }
+
+            '/' if !found_bang && chars.peek().is_some_and(|(_, next)| *next == '/' || *next == '*') => {
+                let (_, opener) = chars.next().unwrap();
+                let mut previous = None;
+
+                column += 1;
+                program.push('/');
+                program.push(opener);
+
+                // Line comments stop before the line break, which
+                // is left to be counted like any other.
+                while let Some(&(_, c)) = chars.peek() {
+                    if opener == '/' && c == '\n' {
+                        break;
+                    }
+
+                    chars.next();
+                    program.push(c);
+
+                    if c == '\n' {
+                        line += 1;
+                        column = 1;
+                    }
+                    else {
+                        column += 1;
+                    }
+
+                    if opener == '*' && previous == Some('*') && c == '/' {
+                        break;
+                    }
+
+                    previous = Some(c);
+                }
+            }
+
+            '!' if found_bang => return Err(BrainfuckError::ExtraBang { pos }),
+
+            '!' => found_bang = true,
+
+            c if found_bang => input.push(c),
+
+            c => program.push(c)
+        }
+    }
+
+    Ok((program, input))
+}
+
+// The order the instructions are given in for a custom mapping.
+pub const MAPPING_ORDER: [char; 8] = ['+', '-', '<', '>', '[', ']', '.', ','];
+
+// Translate code written with different characters for the 8 instructions into
+// brainfuck, where the mapping has the character used for each instruction in
+// the same order as `MAPPING_ORDER`. The usual instruction characters are only
+// instructions if they're mapped to, so any others become spaces, which keeps the
+// positions of everything else the same. Every other character is left as it is.
+pub fn translate_mapping(code: &str, mapping: [char; 8]) -> String {
+    code.chars()
+        .map(|c| match mapping.iter().position(|mapped| *mapped == c) {
+            Some(i) => MAPPING_ORDER[i],
+            None if MAPPING_ORDER.contains(&c) => ' ',
+            None => c
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::run_str;
+
+    // Write brainfuck as Ook!, with each line of it on its own line.
+    fn to_ook(code: &str) -> String {
+        let words = |c| match c {
+            '>' => "Ook. Ook?",
+            '<' => "Ook? Ook.",
+            '+' => "Ook. Ook.",
+            '-' => "Ook! Ook!",
+            '.' => "Ook! Ook.",
+            ',' => "Ook. Ook!",
+            '[' => "Ook! Ook?",
+            ']' => "Ook? Ook!",
+            _ => unreachable!()
+        };
+
+        code.lines()
+            .map(|line| line.chars().map(words).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn ook_hello_world() {
+        let code = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]\n>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let translated = translate_ook(&to_ook(code)).unwrap();
+
+        assert_eq!(translated, code);
+        assert_eq!(run_str(&translated, b"").unwrap(), b"Hello World!\n");
+    }
+
+    #[test]
+    fn unpaired_ook() {
+        let error = translate_ook("Ook. Ook.\nOok! Ook. Ook.").unwrap_err();
+
+        assert!(matches!(error, BrainfuckError::UnpairedOok { pos: Position { line: 2, column: 11, .. } }));
+        assert_eq!(error.message(), "cannot import Ook! code with an unpaired word. (Every instruction is made of two words.)");
+    }
+
+    #[test]
+    fn bang_in_a_comment_is_not_the_input() {
+        let (program, input) = split_bang_input("// Hello!\n/* ! */,.!ab").unwrap();
+
+        assert_eq!(program, "// Hello!\n/* ! */,.");
+        assert_eq!(input, "ab");
+    }
+
+    #[test]
+    fn escaped_bang_in_the_input() {
+        assert_eq!(split_bang_input(",!a\\!").unwrap(), (",".to_string(), "a!".to_string()));
+        assert!(matches!(split_bang_input(",!a!"), Err(BrainfuckError::ExtraBang { .. })));
+    }
+
+    #[test]
+    fn unmapped_instructions_do_nothing() {
+        let mapping = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+        assert_eq!(translate_mapping("aa+g.", mapping), "++ . ");
+        assert_eq!(translate_mapping("+-<>[],.", ['+', '-', '<', '>', '[', ']', '.', ',']), "+-<>[],.");
+    }
+}
@@ -0,0 +1,307 @@
+use crate::error::BrainfuckError;
+use crate::interpreter::{CellSize, EofMode, Options};
+use crate::optimise::{optimise_code, Op};
+use crate::parse::parse_code;
+use crate::sanitise::sanitise_code;
+
+// The languages brainfuck code can be translated into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitTarget {
+    C,
+    Rust,
+    Wat
+}
+
+// Translate brainfuck code into an equivalent program in another language,
+// using the memory size, cell size, EOF mode and output mode from the options.
+pub fn emit_code(code: &str, target: EmitTarget, options: Options) -> Result<String, BrainfuckError> {
+    let brainfuck_code = sanitise_code(code)?;
+
+    // Every target wraps memory blocks around their range, so "[+]" can
+    // be cleared too. Runs of the same instruction get folded into a
+    // single op, which keeps the translated code short.
+    let optimised_code = optimise_code(&parse_code(&brainfuck_code)?, Options { optimise: true, wrap_increments: true, wrap_decrements: true, ..options });
+
+    // Unrecognised characters can't be translated, so they
+    // error straight away rather than when they'd be run.
+    for (op, code_index) in optimised_code.ops.iter().zip(&optimised_code.indexes) {
+        if let Op::Unrecognised(ch) = *op {
+            return Err(BrainfuckError::UnrecognisedChar { pos: brainfuck_code.positions[*code_index], ch });
+        }
+    }
+
+    let program = match target {
+        EmitTarget::C => emit_c(&optimised_code.ops, options),
+        EmitTarget::Rust => emit_rust(&optimised_code.ops, options),
+        EmitTarget::Wat => emit_wat(&optimised_code.ops, options)
+    };
+
+    Ok(program)
+}
+
+// Adds a line for each op, translated by the given function, with the lines
+// inside of while loops indented by one more level than the loop itself.
+fn push_ops(program: &mut String, ops: &[Op], indent: &str, mut depth: usize, mut translate: impl FnMut(Op) -> String) {
+    for op in ops {
+        if let Op::JumpIfNotZero(_) = op {
+            depth -= 1;
+        }
+
+        program.push_str(&indent.repeat(depth));
+        program.push_str(&translate(*op));
+        program.push('\n');
+
+        if let Op::JumpIfZero(_) = op {
+            depth += 1;
+        }
+    }
+}
+
+// The C program doesn't check that the pointer stays inside the memory.
+fn emit_c(ops: &[Op], options: Options) -> String {
+    let (cell_type, signed_type) = match options.cell_size {
+        CellSize::Eight => ("uint8_t", "int8_t"),
+        CellSize::Sixteen => ("uint16_t", "int16_t"),
+        CellSize::ThirtyTwo => ("uint32_t", "int32_t")
+    };
+
+    // Signed memory blocks are kept unsigned, so wrapping around is always
+    // defined, and only turned into signed numbers to be printed.
+    let number = if options.signed_cells {
+        format!("(long) ({}) *p", signed_type)
+    }
+    else {
+        "(unsigned long) *p".to_string()
+    };
+
+    let number_format = if options.signed_cells { "%ld" } else { "%lu" };
+
+    let mut program = String::new();
+
+    program.push_str("#include <stdint.h>\n#include <stdio.h>\n\n");
+    program.push_str(&format!("static {} tape[{}];\n\n", cell_type, options.memory_size));
+    program.push_str("int main(void) {\n");
+    program.push_str(&format!("    {} *p = tape;\n", cell_type));
+    program.push_str("    int c;\n\n");
+
+    push_ops(&mut program, ops, "    ", 1, |op| match op {
+        Op::Add(amount) if amount > 0 => format!("*p += {};", amount),
+        Op::Add(amount) => format!("*p -= {};", -amount),
+        Op::Move(amount) if amount > 0 => format!("p += {};", amount),
+        Op::Move(amount) => format!("p -= {};", -amount),
+
+        Op::Clear => "*p = 0;".to_string(),
+
+        Op::Scan(amount) if amount > 0 => "while (*p) p += 1;".to_string(),
+        Op::Scan(_) => "while (*p) p -= 1;".to_string(),
+
+        Op::JumpIfZero(_) => "while (*p) {".to_string(),
+
+        Op::JumpIfNotZero(_) => "}".to_string(),
+
+        Op::Output => {
+            if options.numeric_output {
+                format!("printf(\"{} \", {});", number_format, number)
+            }
+            else {
+                "putchar(*p);".to_string()
+            }
+        }
+
+        Op::Input => {
+            match options.eof_mode {
+                EofMode::Unchanged => "c = getchar(); if (c != EOF) *p = c;".to_string(),
+                EofMode::Zero => "c = getchar(); *p = c == EOF ? 0 : c;".to_string(),
+                EofMode::Max => format!("c = getchar(); *p = c == EOF ? {} : c;", options.largest_value())
+            }
+        }
+
+        Op::Debug => format!("fprintf(stderr, \"%ld - [{}]\\n\", (long) (p - tape), {});", number_format, number),
+
+        // Already checked for before translating.
+        Op::Unrecognised(_) => unreachable!()
+    });
+
+    program.push_str("\n    return 0;\n}\n");
+
+    program
+}
+
+// The Rust program panics if the pointer leaves the memory, rather than
+// letting it go anywhere like the C program does.
+fn emit_rust(ops: &[Op], options: Options) -> String {
+    let (cell_type, signed_type) = match options.cell_size {
+        CellSize::Eight => ("u8", "i8"),
+        CellSize::Sixteen => ("u16", "i16"),
+        CellSize::ThirtyTwo => ("u32", "i32")
+    };
+
+    // Runs are folded in before translating, so they can add more than a memory
+    // block holds, which wraps around to the same as adding what's left over.
+    let range = options.cell_size.max_value() as u64 + 1;
+
+    let number = if options.signed_cells {
+        format!("tape[p] as {}", signed_type)
+    }
+    else {
+        "tape[p]".to_string()
+    };
+
+    // Only what gets used is declared (or made mutable), so the program
+    // compiles without warnings unless the code never touches the memory.
+    let has_output = ops.contains(&Op::Output);
+    let has_input = ops.contains(&Op::Input);
+    let changes_tape = ops.iter().any(|op| matches!(op, Op::Add(_) | Op::Clear | Op::Input));
+    let moves = ops.iter().any(|op| matches!(op, Op::Move(_) | Op::Scan(_)));
+
+    let mut program = String::new();
+
+    match (has_input, has_output) {
+        (true, true) => program.push_str("use std::io::{self, Read, Write};\n\n"),
+        (true, false) => program.push_str("use std::io::{self, Read};\n\n"),
+        (false, true) => program.push_str("use std::io::{self, Write};\n\n"),
+        (false, false) => {}
+    }
+
+    program.push_str("fn main() {\n");
+    program.push_str(&format!("    let {}tape: Vec<{}> = vec![0; {}];\n", if changes_tape { "mut " } else { "" }, cell_type, options.memory_size));
+    program.push_str(&format!("    let {}p: usize = 0;\n", if moves { "mut " } else { "" }));
+
+    if has_output {
+        program.push_str("    let mut output = io::BufWriter::new(io::stdout().lock());\n");
+    }
+
+    if has_input {
+        program.push_str("    let mut input = io::stdin().lock().bytes();\n");
+    }
+
+    program.push('\n');
+
+    push_ops(&mut program, ops, "    ", 1, |op| match op {
+        Op::Add(amount) if amount > 0 => format!("tape[p] = tape[p].wrapping_add({});", amount as u64 % range),
+        Op::Add(amount) => format!("tape[p] = tape[p].wrapping_sub({});", amount.unsigned_abs() as u64 % range),
+        Op::Move(amount) if amount > 0 => format!("p += {};", amount),
+        Op::Move(amount) => format!("p -= {};", -amount),
+
+        Op::Clear => "tape[p] = 0;".to_string(),
+
+        Op::Scan(amount) if amount > 0 => "while tape[p] != 0 { p += 1; }".to_string(),
+        Op::Scan(_) => "while tape[p] != 0 { p -= 1; }".to_string(),
+
+        Op::JumpIfZero(_) => "while tape[p] != 0 {".to_string(),
+
+        Op::JumpIfNotZero(_) => "}".to_string(),
+
+        Op::Output => {
+            if options.numeric_output {
+                format!("write!(output, \"{{}} \", {}).unwrap();", number)
+            }
+            else {
+                "output.write_all(&[tape[p] as u8]).unwrap();".to_string()
+            }
+        }
+
+        // Whatever has been output is shown before waiting for input.
+        Op::Input => {
+            let flush = if has_output { "output.flush().unwrap(); " } else { "" };
+
+            let eof = match options.eof_mode {
+                EofMode::Unchanged => "{}".to_string(),
+                EofMode::Zero => "tape[p] = 0".to_string(),
+                EofMode::Max => format!("tape[p] = {}", options.largest_value())
+            };
+
+            format!("{}match input.next() {{ Some(Ok(c)) => tape[p] = c.into(), _ => {} }}", flush, eof)
+        }
+
+        Op::Debug => format!("eprintln!(\"{{}} - [{{}}]\", p, {});", number),
+
+        // Already checked for before translating.
+        Op::Unrecognised(_) => unreachable!()
+    });
+
+    program.push_str("}\n");
+
+    program
+}
+
+// The WebAssembly module imports "putchar" and "getchar" functions from "env"
+// to do the output and input (and "putnumber" if numbers are being output),
+// and exports its memory and a "main" function that runs the code. Like the C
+// program, the pointer isn't checked to stay inside the memory.
+fn emit_wat(ops: &[Op], options: Options) -> String {
+    let (load, store, cell_bytes) = match options.cell_size {
+        CellSize::Eight => ("i32.load8_u", "i32.store8", 1),
+        CellSize::Sixteen => ("i32.load16_u", "i32.store16", 2),
+        CellSize::ThirtyTwo => ("i32.load", "i32.store", 4)
+    };
+
+    // Signed memory blocks are loaded with their sign to be output as numbers.
+    let number_load = match options.cell_size {
+        CellSize::Eight if options.signed_cells => "i32.load8_s",
+        CellSize::Sixteen if options.signed_cells => "i32.load16_s",
+        _ => load
+    };
+
+    // Memory is given in pages of 64KiB.
+    let pages = (options.memory_size * cell_bytes).div_ceil(65_536).max(1);
+
+    let mut module = String::new();
+
+    module.push_str("(module\n");
+    module.push_str("  (import \"env\" \"putchar\" (func $putchar (param i32)))\n");
+    module.push_str("  (import \"env\" \"getchar\" (func $getchar (result i32)))\n");
+
+    if options.numeric_output {
+        module.push_str("  (import \"env\" \"putnumber\" (func $putnumber (param i32)))\n");
+    }
+
+    module.push_str(&format!("  (memory (export \"memory\") {})\n\n", pages));
+    module.push_str("  (func (export \"main\")\n");
+    module.push_str("    (local $p i32)\n");
+    module.push_str("    (local $c i32)\n\n");
+
+    push_ops(&mut module, ops, "  ", 2, |op| match op {
+        Op::Add(amount) => format!("local.get $p local.get $p {} i32.const {} i32.add {}", load, amount, store),
+        Op::Move(amount) => format!("local.get $p i32.const {} i32.add local.set $p", amount * cell_bytes as isize),
+
+        Op::Clear => format!("local.get $p i32.const 0 {}", store),
+
+        Op::Scan(amount) => format!("block loop local.get $p {} i32.eqz br_if 1 local.get $p i32.const {} i32.add local.set $p br 0 end end", load, amount * cell_bytes as isize),
+
+        // The block is left when the memory block is 0, and the
+        // loop is gone back to at the end of the body.
+        Op::JumpIfZero(_) => format!("block loop local.get $p {} i32.eqz br_if 1", load),
+
+        Op::JumpIfNotZero(_) => "br 0 end end".to_string(),
+
+        Op::Output => {
+            if options.numeric_output {
+                format!("local.get $p {} call $putnumber", number_load)
+            }
+            else {
+                format!("local.get $p {} call $putchar", load)
+            }
+        }
+
+        // "getchar" returns -1 when there is no more input.
+        Op::Input => {
+            let read = format!("call $getchar local.set $c local.get $c i32.const -1 i32.ne if local.get $p local.get $c {}", store);
+
+            match options.eof_mode {
+                EofMode::Unchanged => format!("{} end", read),
+                EofMode::Zero => format!("{} else local.get $p i32.const 0 {} end", read, store),
+                EofMode::Max => format!("{} else local.get $p i32.const {} {} end", read, options.largest_value() as i32, store)
+            }
+        }
+
+        Op::Debug => "nop ;; #".to_string(),
+
+        // Already checked for before translating.
+        Op::Unrecognised(_) => unreachable!()
+    });
+
+    module.push_str("  )\n)\n");
+
+    module
+}
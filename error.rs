@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::fmt;
+
+// Which way the pointer tried to leave the memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right
+}
+
+// Where an instruction is, both as an index into the sanitised code and as
+// a line and column (starting from 1) in the original code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub index: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+// An error raised while sanitising or executing brainfuck code.
+//
+// Every error is pinned to the position of the instruction that caused
+// it. For errors in comments, the index is where the next instruction
+// would have been in the sanitised code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrainfuckError {
+    UnterminatedComment { pos: Position },
+    StrayComment { pos: Position },
+    UnterminatedLoop { pos: Position },
+    TrailingLoop { pos: Position },
+    UnrecognisedChar { pos: Position, ch: char },
+    UnrecognisedOok { pos: Position, token: String },
+    UnpairedOok { pos: Position },
+    ExtraBang { pos: Position },
+    OutOfBounds { pos: Position, direction: Direction, memory_size: usize },
+    Overflow { pos: Position, limit: u32 },
+    SubZero { pos: Position },
+    Output { pos: Position },
+    StepLimitExceeded { pos: Position, steps: usize },
+    OutputLimitExceeded { pos: Position, bytes: usize }
+}
+
+impl BrainfuckError {
+    // The category shown to the user, eg. "SyntaxError".
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrainfuckError::UnterminatedComment { .. }
+            | BrainfuckError::StrayComment { .. }
+            | BrainfuckError::UnterminatedLoop { .. }
+            | BrainfuckError::TrailingLoop { .. }
+            | BrainfuckError::UnrecognisedChar { .. }
+            | BrainfuckError::UnrecognisedOok { .. }
+            | BrainfuckError::UnpairedOok { .. }
+            | BrainfuckError::ExtraBang { .. } => "SyntaxError",
+
+            BrainfuckError::OutOfBounds { .. } => "OutOfBoundsError",
+
+            BrainfuckError::Overflow { .. } => "OverflowError",
+
+            BrainfuckError::SubZero { .. } => "SubZeroError",
+
+            BrainfuckError::Output { .. } => "OutputError",
+
+            BrainfuckError::StepLimitExceeded { .. } => "StepLimitError",
+
+            BrainfuckError::OutputLimitExceeded { .. } => "OutputLimitError"
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        match self {
+            BrainfuckError::UnterminatedComment { pos }
+            | BrainfuckError::StrayComment { pos }
+            | BrainfuckError::UnterminatedLoop { pos }
+            | BrainfuckError::TrailingLoop { pos }
+            | BrainfuckError::UnrecognisedChar { pos, .. }
+            | BrainfuckError::UnrecognisedOok { pos, .. }
+            | BrainfuckError::UnpairedOok { pos }
+            | BrainfuckError::ExtraBang { pos }
+            | BrainfuckError::OutOfBounds { pos, .. }
+            | BrainfuckError::Overflow { pos, .. }
+            | BrainfuckError::SubZero { pos }
+            | BrainfuckError::Output { pos }
+            | BrainfuckError::StepLimitExceeded { pos, .. }
+            | BrainfuckError::OutputLimitExceeded { pos, .. } => *pos
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            BrainfuckError::UnterminatedComment { .. } => "cannot import code with unterminated multi-line comments. (\"/*\" was found in the code.)".to_string(),
+            BrainfuckError::StrayComment { .. } => "cannot import code with stray comment characters. (\"*/\" was found in the code.)".to_string(),
+            BrainfuckError::UnterminatedLoop { .. } => "cannot import code with unterminated while loops. (Unmatched \"[\" was found in the code.)".to_string(),
+            BrainfuckError::TrailingLoop { .. } => "cannot import code with trailing while loop characters. (Unmatched \"]\" was found in the code.)".to_string(),
+            BrainfuckError::UnrecognisedChar { ch, .. } => format!("unrecognised character '{}' found in code.", ch),
+            BrainfuckError::UnrecognisedOok { token, .. } => format!("unrecognised Ook! instruction \"{}\" found in code.", token),
+            BrainfuckError::UnpairedOok { .. } => "cannot import Ook! code with an unpaired word. (Every instruction is made of two words.)".to_string(),
+            BrainfuckError::ExtraBang { .. } => "cannot split the code from its input more than once. (Another \"!\" was found, which can be written as \"\\!\" in the input.)".to_string(),
+            BrainfuckError::OutOfBounds { direction: Direction::Left, .. } => "cannot move pointer outside of leftward bounds.".to_string(),
+            BrainfuckError::OutOfBounds { direction: Direction::Right, memory_size, .. } => format!("cannot move pointer outside of rightward bounds, past the last of the {} memory blocks.", memory_size),
+            BrainfuckError::Overflow { limit, .. } => format!("cannot increment memory block past integer limit of {}.", limit),
+            BrainfuckError::SubZero { .. } => "cannot decrement memory block below 0.".to_string(),
+            BrainfuckError::Output { .. } => "could not write to the output.".to_string(),
+            BrainfuckError::StepLimitExceeded { steps, .. } => format!("execution was stopped after {} steps, as the step limit was reached.", steps),
+            BrainfuckError::OutputLimitExceeded { bytes, .. } => format!("execution was stopped after {} bytes of output, as the output limit was reached.", bytes)
+        }
+    }
+}
+
+// Shows the error the same way the executable does, without the colours, eg.
+// "SubZeroError: at line 1, column 3 - cannot decrement memory block below 0."
+impl fmt::Display for BrainfuckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let position = self.position();
+
+        write!(f, "{}: at line {}, column {} - {}", self.name(), position.line, position.column, self.message())
+    }
+}
+
+impl Error for BrainfuckError {}
@@ -0,0 +1,149 @@
+use crate::error::BrainfuckError;
+use crate::interpreter::INSTRUCTIONS;
+use crate::sanitise::{sanitise_code, tokenise_code, Token};
+
+// Lay brainfuck code out neatly: every "[" and "]" gets a line of its own,
+// the code inside of a while loop is indented by one more space, and the
+// other instructions are split into lines of at most the given length.
+// Comments are kept, each on their own line, as well as blank lines
+// between blocks of code. Plain text that isn't in a "//" or "/* */"
+// comment is kept as it was written too, just like those comments.
+pub fn format_code(code: &str, instructions_per_line: usize) -> Result<String, BrainfuckError> {
+    // Catches any unmatched brackets before they can mess up the indentation.
+    sanitise_code(code)?;
+
+    let mut formatter = Formatter {
+        formatted: String::new(),
+        line: String::new(),
+        text: String::new(),
+        depth: 0
+    };
+
+    let mut newlines_in_a_row = 0;
+
+    for token in tokenise_code(code)? {
+        match token {
+            Token::Char(c, _) if !INSTRUCTIONS.contains(&c) => {
+                formatter.finish_line();
+                formatter.text.push(c);
+                newlines_in_a_row = 0;
+
+                continue;
+            }
+
+            // Spaces are only kept between the words of plain text.
+            Token::Space => {
+                if !formatter.text.is_empty() {
+                    formatter.text.push(' ');
+                }
+
+                continue;
+            }
+
+            _ => formatter.finish_text()
+        }
+
+        if token == Token::Newline {
+            newlines_in_a_row += 1;
+
+            // Only the first blank line is kept between blocks of code.
+            if newlines_in_a_row == 2 && !formatter.formatted.is_empty() {
+                formatter.finish_line();
+                formatter.formatted.push('\n');
+            }
+
+            continue;
+        }
+
+        newlines_in_a_row = 0;
+
+        match token {
+            Token::Char('[', _) => {
+                formatter.finish_line();
+                formatter.push_line("[");
+                formatter.depth += 1;
+            }
+
+            Token::Char(']', _) => {
+                formatter.finish_line();
+                formatter.depth -= 1;
+                formatter.push_line("]");
+            }
+
+            Token::Char(c, _) => {
+                formatter.line.push(c);
+
+                if formatter.line.chars().count() >= instructions_per_line {
+                    formatter.finish_line();
+                }
+            }
+
+            Token::Comment(comment) => {
+                formatter.finish_line();
+
+                for comment_line in comment.lines() {
+                    formatter.push_line(comment_line.trim());
+                }
+            }
+
+            Token::Newline | Token::Space => {}
+        }
+    }
+
+    formatter.finish_text();
+    formatter.finish_line();
+
+    Ok(formatter.formatted)
+}
+
+struct Formatter {
+    formatted: String,
+
+    // The instructions that haven't been written out yet.
+    line: String,
+
+    // The plain text that hasn't been written out yet, which
+    // gets a line of its own in the same way as a comment.
+    text: String,
+
+    // How many while loops the next line is inside of.
+    depth: usize
+}
+
+impl Formatter {
+    fn push_line(&mut self, text: &str) {
+        self.formatted.push_str(&" ".repeat(self.depth));
+        self.formatted.push_str(text);
+        self.formatted.push('\n');
+    }
+
+    // Write out any instructions waiting on the current line.
+    fn finish_line(&mut self) {
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+
+            self.push_line(&line);
+        }
+    }
+
+    // Write out any plain text waiting to go on its own line.
+    fn finish_text(&mut self) {
+        if !self.text.is_empty() {
+            let text = std::mem::take(&mut self.text);
+
+            self.push_line(text.trim_end());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_keeps_its_spaces() {
+        let formatted = format_code("add one + then print . done", 32).unwrap();
+
+        assert_eq!(formatted, "add one\n+\nthen print\n.\ndone\n");
+    }
+}
@@ -0,0 +1,54 @@
+// Write brainfuck code that prints the given text when it's run, with a line
+// for each byte of it. The code only uses the first two memory blocks, where
+// the second one holds the byte that was printed last, and the first one counts
+// down in a while loop whenever multiplying gets to the next byte quicker. It
+// never takes a memory block past 0 or 255, so it runs without wrapping cells.
+pub fn generate_code(text: &str) -> String {
+    let mut code = String::from(">\n");
+    let mut current = 0;
+
+    for byte in text.bytes() {
+        code.push_str(&change_by(byte as isize - current));
+        code.push_str(".\n");
+
+        current = byte as isize;
+    }
+
+    code
+}
+
+// The shortest code (of the ones tried) that changes the second memory block by
+// the amount, using the first one as a counter so "+++[>+++++<-]>" adds 15.
+fn change_by(amount: isize) -> String {
+    let instruction = if amount > 0 { "+" } else { "-" };
+    let amount = amount.unsigned_abs();
+
+    let mut shortest = instruction.repeat(amount);
+
+    for count in 2..=amount {
+        let step = amount / count;
+        let rest = amount - count * step;
+
+        // The pointer moves and the loop's brackets take 6 more instructions.
+        if count + step + rest + 6 < shortest.len() {
+            shortest = format!("<{}[>{}<-]>{}", "+".repeat(count), instruction.repeat(step), instruction.repeat(rest));
+        }
+    }
+
+    shortest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::run_str;
+
+    #[test]
+    fn generated_code_prints_the_text() {
+        let non_ascii: String = ('\u{80}'..='\u{ff}').chain(['☃', '🦀']).collect();
+
+        for text in ["Hello, World!\n", non_ascii.as_str(), ""] {
+            assert_eq!(run_str(&generate_code(text), b"").unwrap(), text.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,1338 @@
+use std::io::{self, Read, Write};
+
+use crate::error::{BrainfuckError, Direction, Position};
+use crate::optimise::{optimise_code, Op};
+use crate::parse::parse_code;
+use crate::program::Program;
+use crate::sanitise::sanitise_code;
+use crate::state::State;
+
+// The 8 instructions, in the order their counts are kept when profiling.
+pub const INSTRUCTIONS: [char; 8] = ['>', '<', '+', '-', '[', ']', '.', ','];
+
+// The memory starts out this size and doubles whenever the
+// pointer moves past the end, up to the memory size option.
+const INITIAL_MEMORY_SIZE: usize = 1_024;
+
+// What to put in the memory block when "," is used
+// but there is no more input to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EofMode {
+    Unchanged,
+    Zero,
+    Max
+}
+
+// How many bits each memory block has, which decides the
+// largest value a block can hold before it overflows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellSize {
+    Eight,
+    Sixteen,
+    ThirtyTwo
+}
+
+impl CellSize {
+    pub fn max_value(self) -> u32 {
+        match self {
+            CellSize::Eight => u8::MAX as u32,
+            CellSize::Sixteen => u16::MAX as u32,
+            CellSize::ThirtyTwo => u32::MAX
+        }
+    }
+
+    // The value as a signed number, eg. 255 is -1 for 8-bit memory blocks.
+    pub fn signed_value(self, value: u32) -> i64 {
+        match self {
+            CellSize::Eight => value as u8 as i8 as i64,
+            CellSize::Sixteen => value as u16 as i16 as i64,
+            CellSize::ThirtyTwo => value as i32 as i64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    // Wrap memory blocks around to 0 when "+" goes past the largest value,
+    // and to the largest value when "-" goes below 0, instead of erroring.
+    pub wrap_increments: bool,
+    pub wrap_decrements: bool,
+
+    pub wrap_tape: bool,
+
+    // Let the pointer move left of the memory block it started on, growing
+    // the memory on that side as well, up to as many blocks as on the right.
+    pub bidirectional: bool,
+
+    pub eof_mode: EofMode,
+    pub cell_size: CellSize,
+
+    // Treat memory blocks as signed numbers, eg. -128 to 127 for 8-bit blocks,
+    // which always wrap around: "+" on 127 gives -128 and "-" on -128 gives 127.
+    // They hold the same bits as unsigned blocks that wrap (so -1 is kept as 255),
+    // which means "." outputs the same byte, but numbers are shown as signed.
+    pub signed_cells: bool,
+
+    // The most memory blocks the pointer can move across.
+    pub memory_size: usize,
+
+    // Stop running after this many instructions, to catch infinite loops.
+    pub max_steps: Option<usize>,
+
+    // Stop running once this many bytes have been output, to catch "." in an infinite loop.
+    pub max_output: Option<usize>,
+
+    // Count how many times each instruction runs.
+    pub profile: bool,
+
+    // Count how many times each memory block is read or written.
+    pub count_accesses: bool,
+
+    // Make "." print the memory block as a number followed
+    // by a space, instead of as a character.
+    pub numeric_output: bool,
+
+    // Throw a SyntaxError for any character that isn't an instruction, instead
+    // of ignoring it like the rest of a comment.
+    pub strict: bool,
+
+    // Treat "#" as an instruction that stops to show the memory,
+    // rather than as an unrecognised character.
+    pub debug_char: bool,
+
+    // Collapse runs of the same instruction and loops that
+    // clear the memory block, so they run in a single go.
+    pub optimise: bool,
+
+    // Keep what every step changed, so it can be undone with `step_back`.
+    pub record_history: bool,
+
+    // Skip checking memory blocks and the pointer stay in range when adding
+    // and moving, for code that's already known to work. Memory blocks wrap
+    // around their range and the pointer wraps around the memory, which is
+    // all allocated up front, instead of anything erroring.
+    pub unchecked: bool
+}
+
+impl Options {
+    // The bits of the largest number a memory block can hold, eg. 255
+    // for 8-bit blocks, or 127 if they are signed.
+    pub fn largest_value(&self) -> u32 {
+        if self.signed_cells {
+            self.cell_size.max_value() >> 1
+        }
+        else {
+            self.cell_size.max_value()
+        }
+    }
+
+    // The number that a memory block holding the value stands for.
+    pub fn display_value(&self, value: u32) -> i64 {
+        if self.signed_cells {
+            self.cell_size.signed_value(value)
+        }
+        else {
+            value as i64
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            wrap_increments: false,
+            wrap_decrements: false,
+            wrap_tape: false,
+            bidirectional: false,
+            eof_mode: EofMode::Zero,
+            cell_size: CellSize::Eight,
+            signed_cells: false,
+            memory_size: 30_000,
+            max_steps: None,
+            max_output: None,
+            profile: false,
+            count_accesses: false,
+            numeric_output: false,
+            strict: false,
+            debug_char: false,
+            optimise: true,
+            record_history: false,
+            unchecked: false
+        }
+    }
+}
+
+// What a single step changed, which is all it takes to undo it. An op only
+// ever changes the memory block the pointer starts on, so that's the only
+// one that needs to be kept.
+struct Undo {
+    op_index: usize,
+    ptr: usize,
+    value: u32,
+    furthest_ptr: usize,
+    output_bytes: usize,
+    steps: usize,
+
+    // The byte read by ",", which has to be given back to the next "," after undoing.
+    input_byte: Option<u8>
+}
+
+pub struct Interpreter {
+    code: Vec<char>,
+    positions: Vec<Position>,
+
+    // Loops jump straight to the op that matches them, worked out before
+    // running, so going around a loop never builds up any state.
+    ops: Vec<Op>,
+    op_indexes: Vec<usize>,
+    op_index: usize,
+    options: Options,
+    input: Box<dyn Read>,
+
+    memory: Vec<u32>,
+    ptr: usize,
+    furthest_ptr: usize,
+
+    // The index in the memory of the block the pointer started on, which
+    // is only moved along when memory blocks are added to the left of it.
+    origin: usize,
+    has_output: bool,
+    output_bytes: usize,
+    input_bytes: usize,
+    steps: usize,
+    instruction_counts: [usize; 8],
+    loop_iterations: Vec<usize>,
+    cell_accesses: Vec<usize>,
+    reached_debug_char: bool,
+
+    // The steps that can be undone, with the latest at the end, and the bytes
+    // of input they read that still have to be read again, with the next at the end.
+    history: Vec<Undo>,
+    replayed_input: Vec<u8>
+}
+
+// Run the code with the default options, reading "," input from the given
+// bytes, and return everything it output.
+pub fn run_str(code: &str, input: &[u8]) -> Result<Vec<u8>, BrainfuckError> {
+    let mut interpreter = Interpreter::new(&code.parse()?);
+
+    interpreter.set_input(Box::new(io::Cursor::new(input.to_vec())));
+    interpreter.run()
+}
+
+impl Interpreter {
+    pub fn new(program: &Program) -> Interpreter {
+        Interpreter::with_program(program, Options::default())
+    }
+
+    pub fn with_options(code: &str, options: Options) -> Result<Interpreter, BrainfuckError> {
+        Ok(Interpreter::with_program(&code.parse()?, options))
+    }
+
+    // Set up to run a program that has already been parsed, which
+    // can't fail since it has already been checked.
+    pub fn with_program(program: &Program, options: Options) -> Interpreter {
+        // Signed memory blocks are stored as the bits of unsigned ones, so wrapping
+        // between the two ends of the signed range is the same as wrapping them.
+        let options = Options {
+            wrap_increments: options.wrap_increments || options.signed_cells,
+            wrap_decrements: options.wrap_decrements || options.signed_cells,
+            ..options
+        };
+
+        let brainfuck_code = program.sanitised_code();
+        let optimised_code = optimise_code(program.instructions(), options);
+        let loop_iterations = vec![0; optimised_code.ops.len()];
+
+        Interpreter {
+            code: brainfuck_code.code.clone(),
+            positions: brainfuck_code.positions.clone(),
+            ops: optimised_code.ops,
+            op_indexes: optimised_code.indexes,
+            op_index: 0,
+            options,
+            input: Box::new(io::empty()),
+
+            memory: vec![0; if options.unchecked { options.memory_size } else { INITIAL_MEMORY_SIZE.min(options.memory_size) }],
+            ptr: 0,
+            furthest_ptr: 0,
+            origin: 0,
+            has_output: false,
+            output_bytes: 0,
+            input_bytes: 0,
+            steps: 0,
+            instruction_counts: [0; 8],
+            loop_iterations,
+            cell_accesses: Vec::new(),
+            reached_debug_char: false,
+
+            history: Vec::new(),
+            replayed_input: Vec::new()
+        }
+    }
+
+    // Swap in new code to run, keeping the memory and pointer as they are.
+    pub fn load(&mut self, code: &str) -> Result<(), BrainfuckError> {
+        let brainfuck_code = sanitise_code(code)?;
+        let optimised_code = optimise_code(&parse_code(&brainfuck_code)?, self.options);
+
+        self.code = brainfuck_code.code;
+        self.positions = brainfuck_code.positions;
+        self.ops = optimised_code.ops;
+        self.op_indexes = optimised_code.indexes;
+        self.has_output = false;
+        self.restart();
+
+        Ok(())
+    }
+
+    // Where the "," instruction reads from. Defaults to no input at all,
+    // so "," always falls back on the EOF mode.
+    pub fn set_input(&mut self, input: Box<dyn Read>) {
+        self.input = input;
+    }
+
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
+    // Only the memory blocks that have been allocated so far. Everything after
+    // the end of the slice is implicitly 0.
+    pub fn memory(&self) -> &[u32] {
+        &self.memory
+    }
+
+    // The block number and value of every memory block that isn't 0, in order,
+    // which are the ones the memory breakdown shows.
+    pub fn nonzero_cells(&self) -> impl Iterator<Item = (isize, u32)> + '_ {
+        self.memory
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(i, value)| (self.block_number(i), *value))
+    }
+
+    // The sanitised code that gets run.
+    pub fn code(&self) -> &[char] {
+        &self.code
+    }
+
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    // Which memory block the one at the given index of the memory is, counting
+    // from the one the pointer started on, so blocks to the left of it are negative.
+    pub fn block_number(&self, index: usize) -> isize {
+        index as isize - self.origin as isize
+    }
+
+    // Where the instruction at the given index of the sanitised code was in the original code.
+    pub fn position(&self, index: usize) -> Position {
+        self.positions[index]
+    }
+
+    // The index of the next instruction to run in the sanitised code.
+    pub fn code_index(&self) -> usize {
+        match self.op_indexes.get(self.op_index) {
+            Some(code_index) => *code_index,
+            None => self.code.len()
+        }
+    }
+
+    // The block number the pointer is on, like every other place a memory block is shown.
+    pub fn pointer(&self) -> isize {
+        self.block_number(self.ptr)
+    }
+
+    // The index of the memory block the pointer is on in `memory()`, which
+    // is its block number plus the origin.
+    pub fn pointer_index(&self) -> usize {
+        self.ptr
+    }
+
+    // The block number of the furthest right the pointer has
+    // been, used to know how much memory to show in a breakdown.
+    pub fn furthest_pointer(&self) -> isize {
+        self.block_number(self.furthest_ptr)
+    }
+
+    // Whether the last step ran a "#" debug instruction, which
+    // is when the memory around the pointer should be shown.
+    pub fn reached_debug_char(&self) -> bool {
+        self.reached_debug_char
+    }
+
+    // Whether "." has written anything since the code was loaded, which
+    // keeps going across runs. `output_bytes` has how much the last run wrote.
+    pub fn has_output(&self) -> bool {
+        self.has_output
+    }
+
+    // How many bytes "." wrote in the last run.
+    pub fn output_bytes(&self) -> usize {
+        self.output_bytes
+    }
+
+    // How many bytes "," read in the last run.
+    pub fn input_bytes(&self) -> usize {
+        self.input_bytes
+    }
+
+    // How many instructions were executed in the last run.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // How many times each instruction was executed in the last run, in the
+    // same order as `INSTRUCTIONS`. Only counted when profiling is turned on.
+    pub fn instruction_counts(&self) -> [(char, usize); 8] {
+        let mut counts = [(' ', 0); 8];
+
+        for (i, instruction) in INSTRUCTIONS.iter().enumerate() {
+            counts[i] = (*instruction, self.instruction_counts[i]);
+        }
+
+        counts
+    }
+
+    // How many times the body of each while loop ran in the last run, as the index
+    // of its "[" in the sanitised code and the count. Only counted when profiling is
+    // turned on, and loops that were optimised away aren't included.
+    pub fn loop_iterations(&self) -> Vec<(usize, usize)> {
+        self.ops
+            .iter()
+            .enumerate()
+            .filter(|(_, op)| matches!(op, Op::JumpIfZero(_)))
+            .map(|(i, _)| (self.op_indexes[i], self.loop_iterations[i]))
+            .collect()
+    }
+
+    // How many times each memory block was read or written in the last run, where
+    // blocks past the end were never touched. Only counted when counting accesses
+    // is turned on, with a run of "+" or "-" counting once for each instruction.
+    pub fn cell_accesses(&self) -> &[usize] {
+        &self.cell_accesses
+    }
+
+    // Move the pointer straight to a memory block, growing the memory to reach it,
+    // like when starting partway along. Returns false if there is no such block.
+    pub fn set_pointer(&mut self, ptr: usize) -> bool {
+        if ptr >= self.options.memory_size {
+            return false;
+        }
+
+        if ptr >= self.memory.len() {
+            let mut new_size = self.memory.len();
+
+            while new_size <= ptr {
+                new_size *= 2;
+            }
+
+            self.memory.resize(new_size.min(self.options.memory_size), 0);
+        }
+
+        self.ptr = ptr;
+        self.furthest_ptr = self.furthest_ptr.max(ptr);
+
+        true
+    }
+
+    // A snapshot of where the run is up to, which can be saved and restored later.
+    pub fn state(&self) -> State {
+        State {
+            memory: self.memory.clone(),
+            ptr: self.ptr,
+            furthest_ptr: self.furthest_ptr,
+            origin: self.origin,
+            code_index: self.code_index(),
+            input_bytes: self.input_bytes
+        }
+    }
+
+    // Pick a run back up from a saved state, skipping the input that had already
+    // been read, so the next step carries on from there. Returns false and leaves
+    // everything as it was if the state doesn't fit the code or the options.
+    pub fn restore_state(&mut self, state: &State) -> bool {
+        let op_index = if state.code_index == self.code.len() {
+            Some(self.ops.len())
+        }
+        else {
+            self.op_indexes.binary_search(&state.code_index).ok()
+        };
+
+        let max_value = self.options.cell_size.max_value();
+
+        // Memory blocks can only be to the left of the
+        // one the pointer started on if it can move there.
+        let room_on_left = if self.options.bidirectional { self.options.memory_size } else { 0 };
+
+        let memory_fits = !state.memory.is_empty()
+            && state.origin <= room_on_left
+            && state.origin < state.memory.len()
+            && state.memory.len() <= state.origin + self.options.memory_size
+            && state.ptr < state.memory.len()
+            && state.furthest_ptr < state.memory.len()
+            && state.memory.iter().all(|value| *value <= max_value);
+
+        let op_index = match op_index {
+            Some(op_index) if memory_fits => op_index,
+            _ => return false
+        };
+
+        let _ = io::copy(&mut self.input.by_ref().take(state.input_bytes as u64), &mut io::sink());
+
+        self.memory = state.memory.clone();
+        self.ptr = state.ptr;
+        self.furthest_ptr = state.furthest_ptr;
+        self.origin = state.origin;
+        self.op_index = op_index;
+        self.input_bytes = state.input_bytes;
+        self.history.clear();
+
+        true
+    }
+
+    // Run the code, collecting everything printed by "." and returning it.
+    pub fn run(&mut self) -> Result<Vec<u8>, BrainfuckError> {
+        let mut output = Vec::new();
+
+        self.run_with_output(&mut output)?;
+
+        Ok(output)
+    }
+
+    // Run the code from the start, writing everything printed by "." straight to the given output.
+    pub fn run_with_output<W: Write>(&mut self, output: &mut W) -> Result<(), BrainfuckError> {
+        self.restart();
+
+        while self.step(output)? {}
+
+        Ok(())
+    }
+
+    // Go back to the start of the code, keeping the memory and pointer as they are.
+    pub fn restart(&mut self) {
+        self.op_index = 0;
+        self.output_bytes = 0;
+        self.input_bytes = 0;
+        self.steps = 0;
+        self.instruction_counts = [0; 8];
+        self.loop_iterations = vec![0; self.ops.len()];
+        self.cell_accesses.clear();
+        self.history.clear();
+    }
+
+    // Undo the last step, returning false if there was nothing to undo. This needs
+    // the history to be recorded, and anything the step output stays printed
+    // (and gets printed again if the step is run again).
+    pub fn step_back(&mut self) -> bool {
+        let undo = match self.history.pop() {
+            Some(undo) => undo,
+            None => return false
+        };
+
+        self.memory[undo.ptr] = undo.value;
+        self.ptr = undo.ptr;
+        self.furthest_ptr = undo.furthest_ptr;
+        self.op_index = undo.op_index;
+        self.output_bytes = undo.output_bytes;
+        self.steps = undo.steps;
+        self.reached_debug_char = false;
+
+        if let Some(byte) = undo.input_byte {
+            self.input_bytes -= 1;
+            self.replayed_input.push(byte);
+        }
+
+        true
+    }
+
+    // Loops are run by jumping between ops that were matched up before running,
+    // so there's no stack of open loops that could be left over at the end.
+    pub fn is_finished(&self) -> bool {
+        self.op_index >= self.ops.len()
+    }
+
+    // Execute a single op, returning false if there was nothing left to
+    // execute. Without optimising, every op is a single instruction.
+    pub fn step<W: Write>(&mut self, output: &mut W) -> Result<bool, BrainfuckError> {
+        if self.is_finished() {
+            return Ok(false);
+        }
+
+        self.reached_debug_char = false;
+
+        let op = self.ops[self.op_index];
+        let code_index = self.op_indexes[self.op_index];
+        let position = self.positions[code_index];
+
+        // How many of the instructions the op stands in for get run, which
+        // is cut short if the step limit is reached partway through. A loop's
+        // "[" runs once, then the two instructions after it for every time around.
+        let mut count = match op {
+            Op::Clear | Op::Scan(_) => 1 + 2 * self.loop_runs(op, code_index),
+            _ => op.instruction_count()
+        };
+        let mut limit_reached = false;
+
+        if let Some(max_steps) = self.options.max_steps {
+            let steps_left = max_steps - self.steps;
+
+            if steps_left == 0 {
+                return Err(BrainfuckError::StepLimitExceeded { pos: position, steps: self.steps });
+            }
+
+            if count > steps_left {
+                count = steps_left;
+                limit_reached = true;
+            }
+        }
+
+        if self.options.record_history {
+            self.history.push(Undo {
+                op_index: self.op_index,
+                ptr: self.ptr,
+                value: self.memory[self.ptr],
+                furthest_ptr: self.furthest_ptr,
+                output_bytes: self.output_bytes,
+                steps: self.steps,
+                input_byte: None
+            });
+        }
+
+        self.steps += count;
+
+        if self.options.profile {
+            match op {
+                Op::Clear | Op::Scan(_) => {
+                    self.count_instruction('[', 1);
+                    self.count_instruction(self.code[code_index + 1], count / 2);
+                    self.count_instruction(']', (count - 1) / 2);
+                }
+
+                _ => self.count_instruction(op.instruction(), count)
+            }
+        }
+
+        let ptr = self.ptr;
+
+        // Moving the pointer is the only thing that doesn't touch the memory block.
+        if self.options.count_accesses && !matches!(op, Op::Move(_) | Op::Debug | Op::Unrecognised(_)) {
+            if self.cell_accesses.len() <= ptr {
+                self.cell_accesses.resize(ptr + 1, 0);
+            }
+
+            // Only the block a scan starts on is counted, which is the one its "[" reads.
+            self.cell_accesses[ptr] += if matches!(op, Op::Scan(_)) { 1 } else { count };
+        }
+
+        match op {
+            // The largest value is always one less than a power of 2, so
+            // masking with it wraps around the range without a check.
+            Op::Add(amount) if self.options.unchecked => {
+                let change = if amount > 0 { count as u32 } else { (count as u32).wrapping_neg() };
+
+                self.memory[ptr] = self.memory[ptr].wrapping_add(change) & self.options.cell_size.max_value();
+            }
+
+            Op::Move(amount) if self.options.unchecked => {
+                let offset = if amount > 0 { count as isize } else { -(count as isize) };
+
+                self.ptr = (ptr as isize + offset).rem_euclid(self.options.memory_size as isize) as usize;
+                self.furthest_ptr = self.furthest_ptr.max(self.ptr);
+            }
+
+            Op::Add(amount) if amount > 0 => self.increment(count, code_index)?,
+
+            Op::Add(_) => self.decrement(count, code_index)?,
+
+            Op::Move(amount) if amount > 0 => self.move_right(count, code_index)?,
+
+            Op::Move(_) => self.move_left(count, code_index)?,
+
+            // Stopped partway around a clear or scan loop by the step limit,
+            // after running the instruction inside of it this many times.
+            Op::Clear | Op::Scan(_) if limit_reached => {
+                let runs = count / 2;
+
+                match op {
+                    Op::Clear if self.code[code_index + 1] == '-' => self.memory[ptr] -= runs as u32,
+
+                    // Going all the way around adds one more than the largest value, which
+                    // doesn't fit in a 32-bit memory block, so it's wrapped back to 0 here.
+                    Op::Clear => {
+                        let cells = self.options.cell_size.max_value() as u64 + 1;
+
+                        self.memory[ptr] = ((self.memory[ptr] as u64 + runs as u64) % cells) as u32;
+                    }
+
+                    Op::Scan(amount) if amount > 0 => {
+                        self.ptr += runs;
+                        self.furthest_ptr = self.furthest_ptr.max(self.ptr);
+                    }
+
+                    _ => self.ptr -= runs
+                }
+            }
+
+            Op::Clear => self.memory[ptr] = 0,
+
+            Op::Scan(amount) if amount > 0 => self.scan_right(code_index)?,
+
+            Op::Scan(_) => self.scan_left(code_index)?,
+
+            Op::JumpIfZero(end) => {
+                // Skip the whole loop if the cell the pointer lands on is 0,
+                // resuming after the matching "]".
+                if self.memory[ptr] == 0 {
+                    self.op_index = end;
+                }
+                else if self.options.profile {
+                    self.loop_iterations[self.op_index] += 1;
+                }
+            }
+
+            Op::JumpIfNotZero(start) => {
+                // Send the code pointer back to the start of the while loop
+                // if the cell the pointer lands on is above 0.
+                if self.memory[ptr] > 0 {
+                    self.op_index = start;
+
+                    if self.options.profile {
+                        self.loop_iterations[start] += 1;
+                    }
+                }
+            }
+
+            Op::Output => {
+                if self.options.max_output.is_some_and(|max_output| self.output_bytes >= max_output) {
+                    return Err(BrainfuckError::OutputLimitExceeded { pos: position, bytes: self.output_bytes });
+                }
+
+                let written = if self.options.numeric_output {
+                    let number = format!("{} ", self.options.display_value(self.memory[ptr]));
+
+                    output.write_all(number.as_bytes()).map(|_| number.len())
+                }
+                else {
+                    // Output is byte-oriented, so only the lowest byte of the
+                    // memory block gets written, exactly as it is. Finished lines
+                    // are flushed, so buffered output still shows up as it goes.
+                    let byte = self.memory[ptr] as u8;
+
+                    output.write_all(&[byte])
+                        .and_then(|_| if byte == b'\n' { output.flush() } else { Ok(()) })
+                        .map(|_| 1)
+                };
+
+                match written {
+                    Ok(bytes) => self.output_bytes += bytes,
+                    Err(_) => return Err(BrainfuckError::Output { pos: position })
+                }
+
+                self.has_output = true;
+            }
+
+            Op::Input => {
+                // Anything waiting to be output (like a prompt) is shown before waiting for input.
+                if output.flush().is_err() {
+                    return Err(BrainfuckError::Output { pos: position });
+                }
+
+                let mut input_byte = [0];
+
+                // Input that was given back by undoing a step gets read again first.
+                let read = match self.replayed_input.pop() {
+                    Some(byte) => {
+                        input_byte[0] = byte;
+                        Ok(1)
+                    }
+
+                    None => self.input.read(&mut input_byte)
+                };
+
+                match read {
+                    Ok(1) => {
+                        self.memory[ptr] = input_byte[0] as u32;
+                        self.input_bytes += 1;
+
+                        if let Some(undo) = self.history.last_mut() {
+                            undo.input_byte = Some(input_byte[0]);
+                        }
+                    }
+
+                    // There is no more input, so fall back on the EOF mode.
+                    _ => {
+                        match self.options.eof_mode {
+                            EofMode::Unchanged => {}
+                            EofMode::Zero => self.memory[ptr] = 0,
+                            EofMode::Max => self.memory[ptr] = self.options.largest_value()
+                        }
+                    }
+                }
+            }
+
+            Op::Debug => self.reached_debug_char = true,
+
+            Op::Unrecognised(ch) => {
+                return Err(BrainfuckError::UnrecognisedChar { pos: position, ch });
+            }
+        }
+
+        if limit_reached {
+            // A loop goes back and forth between the two instructions after its "[".
+            let next_index = match op {
+                Op::Clear | Op::Scan(_) => code_index + 2 - count % 2,
+                _ => code_index + count
+            };
+
+            return Err(BrainfuckError::StepLimitExceeded { pos: self.positions[next_index], steps: self.steps });
+        }
+
+        self.op_index += 1;
+
+        Ok(true)
+    }
+
+    fn count_instruction(&mut self, instruction: char, count: usize) {
+        if let Some(i) = INSTRUCTIONS.iter().position(|c| *c == instruction) {
+            self.instruction_counts[i] += count;
+        }
+    }
+
+    // How many times around a clear or scan loop would go without optimising, which
+    // for a scan is how far away the 0 it lands on is. Everything after the memory
+    // that's been grown so far is 0, or past the edge of the memory.
+    fn loop_runs(&self, op: Op, code_index: usize) -> usize {
+        let value = self.memory[self.ptr];
+
+        match op {
+            _ if value == 0 => 0,
+
+            Op::Clear if self.code[code_index + 1] == '-' => value as usize,
+
+            // "[+]" goes up to the largest value and wraps around to 0.
+            Op::Clear => (self.options.cell_size.max_value() - value) as usize + 1,
+
+            Op::Scan(amount) if amount > 0 => match self.memory[self.ptr..].iter().position(|value| *value == 0) {
+                Some(offset) => offset,
+                None => self.memory.len() - self.ptr
+            },
+
+            _ => match self.memory[..=self.ptr].iter().rposition(|value| *value == 0) {
+                Some(i) => self.ptr - i,
+                None => self.ptr + 1
+            }
+        }
+    }
+
+    // Run "+" the given number of times, starting from the instruction at the code index.
+    fn increment(&mut self, count: usize, code_index: usize) -> Result<(), BrainfuckError> {
+        let max_value = self.options.cell_size.max_value();
+        let value = self.memory[self.ptr];
+        let increments_left = (max_value - value) as usize;
+
+        if count <= increments_left {
+            self.memory[self.ptr] = value + count as u32;
+        }
+        else if self.options.wrap_increments {
+            // Wrap back around to the bottom of the range, as many times as it takes.
+            let range = max_value as u64 + 1;
+
+            self.memory[self.ptr] = ((value as u64 + count as u64) % range) as u32;
+        }
+        else {
+            // The instruction that goes past the limit is the one that errors.
+            self.memory[self.ptr] = max_value;
+
+            return Err(BrainfuckError::Overflow { pos: self.positions[code_index + increments_left], limit: max_value });
+        }
+
+        Ok(())
+    }
+
+    // Run "-" the given number of times, starting from the instruction at the code index.
+    fn decrement(&mut self, count: usize, code_index: usize) -> Result<(), BrainfuckError> {
+        let value = self.memory[self.ptr];
+
+        if count <= value as usize {
+            self.memory[self.ptr] = value - count as u32;
+        }
+        else if self.options.wrap_decrements {
+            // Wrap back around to the top of the range, as many times as it takes.
+            let range = self.options.cell_size.max_value() as u64 + 1;
+
+            self.memory[self.ptr] = ((value as u64 + range - count as u64 % range) % range) as u32;
+        }
+        else {
+            self.memory[self.ptr] = 0;
+
+            return Err(BrainfuckError::SubZero { pos: self.positions[code_index + value as usize] });
+        }
+
+        Ok(())
+    }
+
+    // Run ">" the given number of times, starting from the instruction at the code index.
+    // A whole run of moves is checked against the bounds at once, rather than one
+    // move at a time, and so is "<" below.
+    fn move_right(&mut self, count: usize, code_index: usize) -> Result<(), BrainfuckError> {
+        let last_block = self.last_block();
+        let moves_left = last_block - self.ptr;
+        let mut result = Ok(());
+
+        if count <= moves_left {
+            self.ptr += count;
+        }
+        else if self.options.wrap_tape {
+            // Wrap back around to the first memory block, passing
+            // through the last one, so the memory is grown all the way.
+            self.ptr = (self.ptr + count) % self.options.memory_size;
+            self.memory.resize(self.options.memory_size, 0);
+            self.furthest_ptr = last_block;
+        }
+        else {
+            // Gone out of rightward bounds
+            self.ptr = last_block;
+
+            result = Err(BrainfuckError::OutOfBounds { pos: self.positions[code_index + moves_left], direction: Direction::Right, memory_size: self.options.memory_size });
+        }
+
+        // Grow the memory, zero-filling the new blocks.
+        if self.ptr >= self.memory.len() {
+            let mut new_size = self.memory.len();
+
+            while new_size <= self.ptr {
+                new_size *= 2;
+            }
+
+            self.memory.resize(new_size.min(last_block + 1), 0);
+        }
+
+        // Keep record of furthest pointer for
+        // when we print the memory cells.
+        if self.ptr > self.furthest_ptr {
+            self.furthest_ptr = self.ptr;
+        }
+
+        result
+    }
+
+    // Run "[>]", starting from the "[" at the code index, by looking for the first
+    // 0 from the pointer onwards. Everything after the memory that's been grown
+    // so far is 0, so the search never has to go any further than that.
+    fn scan_right(&mut self, code_index: usize) -> Result<(), BrainfuckError> {
+        match self.memory[self.ptr..].iter().position(|value| *value == 0) {
+            Some(offset) => {
+                self.ptr += offset;
+
+                if self.ptr > self.furthest_ptr {
+                    self.furthest_ptr = self.ptr;
+                }
+
+                Ok(())
+            }
+
+            // The first memory block that hasn't been grown yet is the 0.
+            None if self.memory.len() <= self.last_block() => {
+                self.move_right(self.memory.len() - self.ptr, code_index + 1)
+            }
+
+            None => {
+                // Gone out of rightward bounds
+                self.ptr = self.last_block();
+                self.furthest_ptr = self.ptr;
+
+                Err(BrainfuckError::OutOfBounds { pos: self.positions[code_index + 1], direction: Direction::Right, memory_size: self.options.memory_size })
+            }
+        }
+    }
+
+    // Run "[<]", starting from the "[" at the code index, by looking
+    // for the last 0 up to and including the pointer.
+    fn scan_left(&mut self, code_index: usize) -> Result<(), BrainfuckError> {
+        match self.memory[..=self.ptr].iter().rposition(|value| *value == 0) {
+            Some(i) => {
+                self.ptr = i;
+                Ok(())
+            }
+
+            None => match self.options.bidirectional.then(|| self.grow_left(1)).flatten() {
+                // Every new memory block is 0, so the nearest is the last one added.
+                Some(added) => {
+                    self.ptr = added - 1;
+                    Ok(())
+                }
+
+                None => {
+                    // Gone out of leftward bounds
+                    self.ptr = 0;
+
+                    Err(BrainfuckError::OutOfBounds { pos: self.positions[code_index + 1], direction: Direction::Left, memory_size: self.options.memory_size })
+                }
+            }
+        }
+    }
+
+    // How many more memory blocks could be added to the left.
+    fn room_on_left(&self) -> usize {
+        self.options.memory_size - self.origin
+    }
+
+    // The index of the last memory block on the right.
+    fn last_block(&self) -> usize {
+        self.origin + self.options.memory_size - 1
+    }
+
+    // Add at least the given number of memory blocks to the left of the memory,
+    // shifting along everything that points into it, and return how many were
+    // added. The memory at least doubles, like it does when growing to the right,
+    // so moving left a block at a time doesn't have to shift it every time.
+    fn grow_left(&mut self, count: usize) -> Option<usize> {
+        let room = self.room_on_left();
+
+        if count > room {
+            return None;
+        }
+
+        let added = count.max(self.memory.len()).min(room);
+
+        self.memory.splice(0..0, std::iter::repeat_n(0, added));
+
+        if !self.cell_accesses.is_empty() {
+            self.cell_accesses.splice(0..0, std::iter::repeat_n(0, added));
+        }
+
+        self.ptr += added;
+        self.furthest_ptr += added;
+        self.origin += added;
+
+        for undo in &mut self.history {
+            undo.ptr += added;
+            undo.furthest_ptr += added;
+        }
+
+        Some(added)
+    }
+
+    // Run "<" the given number of times, starting from the instruction at the code index.
+    fn move_left(&mut self, count: usize, code_index: usize) -> Result<(), BrainfuckError> {
+        if count <= self.ptr || (self.options.bidirectional && self.grow_left(count - self.ptr).is_some()) {
+            self.ptr -= count;
+        }
+        else if self.options.bidirectional {
+            // Gone out of leftward bounds, after using up
+            // all of the memory there was room for.
+            let room = self.room_on_left();
+            let moves_left = self.ptr + room;
+
+            if room > 0 {
+                self.grow_left(room);
+            }
+
+            self.ptr = 0;
+
+            return Err(BrainfuckError::OutOfBounds { pos: self.positions[code_index + moves_left], direction: Direction::Left, memory_size: self.options.memory_size });
+        }
+        else if self.options.wrap_tape {
+            // Wrap back around to the last memory block, which
+            // means the memory has to be grown all the way.
+            let memory_size = self.options.memory_size;
+
+            self.ptr = (self.ptr + memory_size - count % memory_size) % memory_size;
+            self.memory.resize(memory_size, 0);
+            self.furthest_ptr = memory_size - 1;
+        }
+        else {
+            // Gone out of leftward bounds
+            let moves_left = self.ptr;
+
+            self.ptr = 0;
+
+            return Err(BrainfuckError::OutOfBounds { pos: self.positions[code_index + moves_left], direction: Direction::Left, memory_size: self.options.memory_size });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::BufWriter;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn bidirectional(code: &str) -> Interpreter {
+        let options = Options { bidirectional: true, ..Options::default() };
+        let mut interpreter = Interpreter::with_options(code, options).unwrap();
+
+        interpreter.run().unwrap();
+        interpreter
+    }
+
+    fn run_with(code: &str, options: Options) -> (Interpreter, Vec<u8>) {
+        let mut interpreter = Interpreter::with_options(code, options).unwrap();
+        let output = interpreter.run().unwrap();
+
+        (interpreter, output)
+    }
+
+    fn unoptimised() -> Options {
+        Options { optimise: false, ..Options::default() }
+    }
+
+    #[test]
+    fn optimising_gives_the_same_output() {
+        let code = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let (_, optimised) = run_with(code, Options::default());
+        let (_, unoptimised) = run_with(code, unoptimised());
+
+        assert_eq!(optimised, b"Hello World!\n");
+        assert_eq!(optimised, unoptimised);
+    }
+
+    #[test]
+    fn clear_loop_on_200() {
+        let code = format!("{}[-]", "+".repeat(200));
+        let (optimised, _) = run_with(&code, Options::default());
+        let (unoptimised, _) = run_with(&code, unoptimised());
+
+        assert_eq!(optimised.memory()[0], 0);
+        assert_eq!(optimised.steps(), 200 + 1 + 2 * 200);
+        assert_eq!(optimised.steps(), unoptimised.steps());
+    }
+
+    #[test]
+    fn scan_lands_on_the_first_0() {
+        let code = "+>+>+>>+<<<<[>]";
+        let (optimised, _) = run_with(code, Options::default());
+        let (unoptimised, _) = run_with(code, unoptimised());
+
+        assert_eq!(optimised.pointer(), 3);
+        assert_eq!(optimised.steps(), unoptimised.steps());
+    }
+
+    #[test]
+    fn step_limit_stops_partway_around_a_clear_loop() {
+        let code = "+++++[-]";
+        let options = Options { max_steps: Some(9), ..Options::default() };
+        let mut optimised = Interpreter::with_options(code, options).unwrap();
+        let mut unoptimised = Interpreter::with_options(code, Options { optimise: false, ..options }).unwrap();
+
+        assert!(matches!(optimised.run(), Err(BrainfuckError::StepLimitExceeded { steps: 9, .. })));
+        assert!(matches!(unoptimised.run(), Err(BrainfuckError::StepLimitExceeded { steps: 9, .. })));
+        assert_eq!(optimised.memory()[0], unoptimised.memory()[0]);
+    }
+
+    #[test]
+    fn step_limit_inside_a_clear_loop_matches_the_unoptimised_memory() {
+        for code in ["+[+]", "-[-]", "+++++[-]"] {
+            for max_steps in [1, 2, 3, 4, 100, 509, 510, 511, 512] {
+                let options = Options { wrap_increments: true, wrap_decrements: true, max_steps: Some(max_steps), ..Options::default() };
+                let mut optimised = Interpreter::with_options(code, options).unwrap();
+                let mut unoptimised = Interpreter::with_options(code, Options { optimise: false, ..options }).unwrap();
+
+                let _ = optimised.run();
+                let _ = unoptimised.run();
+
+                assert_eq!(optimised.memory()[0], unoptimised.memory()[0], "{code} stopped after {max_steps} steps");
+            }
+        }
+    }
+
+    #[test]
+    fn step_limit_at_the_end_of_a_32_bit_clear_loop() {
+        let options = Options {
+            cell_size: CellSize::ThirtyTwo,
+            wrap_increments: true,
+            max_steps: Some(8_589_934_591),
+            ..Options::default()
+        };
+        let mut interpreter = Interpreter::with_options("+[+]", options).unwrap();
+
+        assert!(matches!(interpreter.run(), Err(BrainfuckError::StepLimitExceeded { .. })));
+        assert_eq!(interpreter.memory()[0], 0);
+    }
+
+    #[test]
+    fn loop_on_a_zero_cell_is_skipped() {
+        for options in [Options::default(), unoptimised()] {
+            let (interpreter, output) = run_with("[+++.]", options);
+
+            assert!(output.is_empty());
+            assert!(interpreter.memory().iter().all(|value| *value == 0));
+        }
+    }
+
+    #[test]
+    fn triple_nested_loops() {
+        for options in [Options::default(), unoptimised()] {
+            let (interpreter, output) = run_with("++[>++[>++[>+<-]<-]<-]>>>+.", options);
+
+            assert_eq!(output, [9]);
+            assert_eq!(interpreter.memory()[..4], [0, 0, 0, 9]);
+        }
+    }
+
+    #[test]
+    fn wrap_tape_goes_from_the_first_block_to_the_last() {
+        let options = Options { wrap_tape: true, memory_size: 10, ..Options::default() };
+        let (interpreter, _) = run_with("<+", options);
+
+        assert_eq!(interpreter.pointer(), 9);
+        assert_eq!(interpreter.memory()[9], 1);
+    }
+
+    #[test]
+    fn wrap_tape_goes_from_the_last_block_to_the_first() {
+        let options = Options { wrap_tape: true, memory_size: 10, ..Options::default() };
+        let (interpreter, _) = run_with(">>>>>>>>>+>+", options);
+
+        assert_eq!(interpreter.pointer(), 0);
+        assert_eq!(interpreter.memory()[..], [1, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn millions_of_loop_iterations_keep_no_extra_state() {
+        let code = "++++++++++[>++++++++++<-]>[>++++++++++<-]>[>++++++++++<-]>[>++++++++++<-]>[>++++++++++<-]>[->+<]";
+        let options = Options { cell_size: CellSize::ThirtyTwo, profile: true, ..Options::default() };
+        let (interpreter, _) = run_with(code, options);
+
+        assert_eq!(interpreter.memory()[6], 1_000_000);
+        assert_eq!(interpreter.loop_iterations().last(), Some(&(code.len() - 6, 1_000_000)));
+
+        // Nothing is pushed or grown per time around a loop.
+        assert!(interpreter.history.is_empty());
+        assert_eq!(interpreter.memory().len(), INITIAL_MEMORY_SIZE);
+        assert_eq!(interpreter.loop_iterations.len(), interpreter.ops.len());
+    }
+
+    #[test]
+    fn values_that_are_not_characters_write_their_lowest_byte() {
+        // 0xD800 is a surrogate and 0x110000 is past the last character, and both end in a 0 byte.
+        let code = format!(
+            "{0}[>{1}<-]>.<{0}[>>{0}<<-]>>[>{2}<-]>.",
+            "+".repeat(256), "+".repeat(216), "+".repeat(17)
+        );
+        let options = Options { cell_size: CellSize::ThirtyTwo, ..Options::default() };
+        let (interpreter, output) = run_with(&code, options);
+
+        assert_eq!(interpreter.memory()[1], 0xD800);
+        assert_eq!(interpreter.memory()[3], 0x110000);
+        assert_eq!(output, [0, 0]);
+    }
+
+    #[test]
+    fn unchecked_scan_wraps_around_the_memory() {
+        let options = Options { unchecked: true, memory_size: 5, ..Options::default() };
+        let (interpreter, _) = run_with("+>+>+>>+[>]", options);
+
+        assert_eq!(interpreter.pointer(), 3);
+    }
+
+    // Output that can still be looked at while the interpreter holds onto it.
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buffer);
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Input that keeps what had been output by the time it was read from.
+    struct PromptedInput {
+        output: Rc<RefCell<Vec<u8>>>,
+        output_when_read: Rc<RefCell<Option<Vec<u8>>>>
+    }
+
+    impl Read for PromptedInput {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            *self.output_when_read.borrow_mut() = Some(self.output.borrow().clone());
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn prompt_is_shown_before_input() {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let output_when_read = Rc::new(RefCell::new(None));
+
+        let mut interpreter = Interpreter::with_options("++++++++[>++++++++<-]>+.,", Options::default()).unwrap();
+
+        interpreter.set_input(Box::new(PromptedInput { output: output.clone(), output_when_read: output_when_read.clone() }));
+        interpreter.run_with_output(&mut BufWriter::new(SharedOutput(output))).unwrap();
+
+        assert_eq!(*output_when_read.borrow(), Some(b"A".to_vec()));
+    }
+
+    #[test]
+    fn increments_wrap_to_0() {
+        let code = format!("{}.", "+".repeat(256));
+        let options = Options { wrap_increments: true, ..Options::default() };
+
+        assert_eq!(run_with(&code, options).1, [0]);
+        assert!(matches!(run_str(&code, b""), Err(BrainfuckError::Overflow { limit: 255, .. })));
+    }
+
+    #[test]
+    fn decrements_wrap_to_the_largest_value() {
+        let options = Options { wrap_decrements: true, ..Options::default() };
+
+        assert_eq!(run_with("-.", options).1, [255]);
+        assert!(matches!(run_str("-.", b""), Err(BrainfuckError::SubZero { .. })));
+    }
+
+    #[test]
+    fn multibyte_characters_are_read_a_byte_at_a_time() {
+        let output = run_str(",.,.,", "é".as_bytes()).unwrap();
+
+        assert_eq!(output, "é".as_bytes());
+    }
+
+    #[test]
+    fn furthest_pointer_is_a_block_number() {
+        let interpreter = bidirectional("<+>>");
+
+        assert_eq!(interpreter.furthest_pointer(), 1);
+        assert_eq!(interpreter.nonzero_cells().collect::<Vec<_>>(), [(-1, 1)]);
+    }
+
+    #[test]
+    fn moving_far_left_and_writing_there() {
+        let code = format!("{}+{}++", "<".repeat(5_000), ">".repeat(5_000));
+        let interpreter = bidirectional(&code);
+
+        assert_eq!(interpreter.pointer(), 0);
+        assert_eq!(interpreter.nonzero_cells().collect::<Vec<_>>(), [(-5_000, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn saved_state_keeps_blocks_left_of_the_start() {
+        let code = "<<+>>+";
+        let interpreter = bidirectional(code);
+        let state = State::from_bytes(&interpreter.state().to_bytes()).unwrap();
+
+        let options = Options { bidirectional: true, ..Options::default() };
+        let mut restored = Interpreter::with_options(code, options).unwrap();
+
+        assert!(restored.restore_state(&state));
+        assert_eq!(restored.nonzero_cells().collect::<Vec<_>>(), [(-2, 1), (0, 1)]);
+        assert_eq!(restored.pointer(), 0);
+    }
+
+    #[test]
+    fn state_left_of_the_start_needs_bidirectional() {
+        let state = bidirectional("<+").state();
+        let mut interpreter = Interpreter::with_options("<+", Options::default()).unwrap();
+
+        assert!(!interpreter.restore_state(&state));
+    }
+}
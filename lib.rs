@@ -0,0 +1,25 @@
+mod dialect;
+mod emit;
+mod error;
+mod format;
+mod generate;
+mod interpreter;
+mod lint;
+mod optimise;
+mod parse;
+mod program;
+mod sanitise;
+mod state;
+
+pub use dialect::{split_bang_input, translate_mapping, translate_ook, MAPPING_ORDER};
+pub use emit::{emit_code, EmitTarget};
+pub use error::{BrainfuckError, Direction, Position};
+pub use format::format_code;
+pub use generate::generate_code;
+pub use interpreter::{run_str, CellSize, EofMode, Interpreter, Options, INSTRUCTIONS};
+pub use lint::{lint_code, LintWarning};
+pub use optimise::{optimise_code, Op, OptimisedCode};
+pub use parse::{parse_code, Instruction};
+pub use program::Program;
+pub use sanitise::{sanitise_code, SanitisedCode};
+pub use state::State;
@@ -0,0 +1,100 @@
+use crate::error::Position;
+use crate::sanitise::SanitisedCode;
+
+// Something in the code that is allowed to run, but is
+// most likely a mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    // "[]", which either gets skipped or loops forever.
+    EmptyLoop { pos: Position },
+
+    // "+-" or "-+", which leaves the memory block as it was.
+    UndoneChange { pos: Position },
+
+    // A while loop that can never change the memory block it checks,
+    // like "[>.<]", so it loops forever once it's entered.
+    InfiniteLoop { pos: Position }
+}
+
+impl LintWarning {
+    pub fn position(&self) -> Position {
+        match *self {
+            LintWarning::EmptyLoop { pos }
+            | LintWarning::UndoneChange { pos }
+            | LintWarning::InfiniteLoop { pos } => pos
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            LintWarning::EmptyLoop { .. } => "empty while loop, which either does nothing or never ends.".to_string(),
+            LintWarning::UndoneChange { .. } => "memory block is changed and then immediately changed back.".to_string(),
+            LintWarning::InfiniteLoop { .. } => "while loop never changes the memory block it checks, so it never ends once entered.".to_string()
+        }
+    }
+}
+
+// Look through the sanitised code for constructs that can't do anything
+// useful, like empty loops, in the order they appear in the code.
+pub fn lint_code(code: &SanitisedCode) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < code.code.len() {
+        let pos = code.positions[i];
+
+        let warning = match (code.code[i], code.code[i + 1]) {
+            ('[', ']') => Some(LintWarning::EmptyLoop { pos }),
+            ('+', '-') | ('-', '+') => Some(LintWarning::UndoneChange { pos }),
+            _ => None
+        };
+
+        match warning {
+            Some(warning) => {
+                warnings.push(warning);
+
+                // Both characters are part of the warning, so
+                // don't let the second one start another.
+                i += 2;
+            }
+
+            None => i += 1
+        }
+    }
+
+    for (i, c) in code.code.iter().enumerate() {
+        if *c == '[' && never_changes_guard(&code.code[i + 1..code.jump_table[i]]) {
+            warnings.push(LintWarning::InfiniteLoop { pos: code.positions[i] });
+        }
+    }
+
+    warnings.sort_by_key(|warning| warning.position().index);
+
+    warnings
+}
+
+// Whether the body of a while loop always leaves the memory block the loop
+// checks as it was. This is only worked out for loops without any loops inside
+// of them, which end with the pointer back where it started, and empty loops
+// are left to their own warning.
+fn never_changes_guard(body: &[char]) -> bool {
+    if body.is_empty() {
+        return false;
+    }
+
+    let mut offset: isize = 0;
+
+    for c in body {
+        match c {
+            '>' => offset += 1,
+            '<' => offset -= 1,
+            '+' | '-' | ',' if offset == 0 => return false,
+            '+' | '-' | ',' | '.' => {}
+
+            // Anything else could do more than can be worked out here.
+            _ => return false
+        }
+    }
+
+    offset == 0
+}
@@ -0,0 +1,187 @@
+use crate::interpreter::Options;
+use crate::parse::Instruction;
+
+// An operation the interpreter runs, standing in for one or
+// more instructions in the sanitised code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    // Add to (or subtract from, if negative) the memory block.
+    Add(isize),
+
+    // Move the pointer right (or left, if negative).
+    Move(isize),
+
+    // Set the memory block to 0, standing in for "[-]".
+    Clear,
+
+    // Move the pointer right (or left, if negative) one block at a time
+    // until it lands on a 0, standing in for "[>]" and "[<]".
+    Scan(isize),
+
+    // "[" and "]", holding the index of the matching op.
+    JumpIfZero(usize),
+    JumpIfNotZero(usize),
+
+    Output,
+    Input,
+
+    // "#", which shows the memory around the pointer when
+    // the debug character is allowed.
+    Debug,
+
+    // A character that isn't an instruction, which errors
+    // when it's run in strict mode.
+    Unrecognised(char)
+}
+
+impl Op {
+    // How many steps running the op counts as. Runs take a step for each
+    // instruction in them, so the step limit works the same as without
+    // optimising. How many a clear or scan loop takes depends on the memory,
+    // so the interpreter works those out as it runs them.
+    pub fn instruction_count(self) -> usize {
+        match self {
+            Op::Add(amount) | Op::Move(amount) => amount.unsigned_abs(),
+            _ => 1
+        }
+    }
+
+    // The instruction the op is made of.
+    pub fn instruction(self) -> char {
+        match self {
+            Op::Add(amount) => if amount > 0 { '+' } else { '-' },
+            Op::Move(amount) => if amount > 0 { '>' } else { '<' },
+            Op::Clear | Op::Scan(_) | Op::JumpIfZero(_) => '[',
+            Op::JumpIfNotZero(_) => ']',
+            Op::Output => '.',
+            Op::Input => ',',
+            Op::Debug => '#',
+            Op::Unrecognised(ch) => ch
+        }
+    }
+}
+
+// The ops, along with the index in the sanitised code of
+// the first instruction that each one stands in for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimisedCode {
+    pub ops: Vec<Op>,
+    pub indexes: Vec<usize>
+}
+
+// Flatten the instructions into ops, with while loops turned into jumps.
+// When optimising, runs of the same instruction (like "+++++" or ">>>>")
+// are collapsed into a single op and loops that clear the memory block
+// are replaced with a single op, otherwise every instruction gets an op
+// of its own.
+pub fn optimise_code(instructions: &[Instruction], options: Options) -> OptimisedCode {
+    let mut optimised = OptimisedCode {
+        ops: Vec::new(),
+        indexes: Vec::new()
+    };
+
+    flatten(instructions, options, &mut optimised, 0);
+
+    optimised
+}
+
+// Adds the ops for one level of the instruction tree, where the first
+// instruction is at the given index of the sanitised code. Returns the
+// index just after the last instruction.
+fn flatten(instructions: &[Instruction], options: Options, optimised: &mut OptimisedCode, mut code_index: usize) -> usize {
+    let mut i = 0;
+
+    while i < instructions.len() {
+        let current = &instructions[i];
+
+        if let Instruction::Loop(body) = current {
+            // "[-]" always leaves the memory block at 0, and so does "[+]"
+            // when "+" wraps memory blocks around instead of overflowing.
+            let is_clear_loop = match body.as_slice() {
+                [Instruction::Decrement] => true,
+                [Instruction::Increment] => options.wrap_increments,
+                _ => false
+            };
+
+            // "[>]" and "[<]" land on the nearest 0 in that direction, unless the
+            // pointer can wrap around the memory and never find one, which it
+            // does with --wrap-tape and when nothing is being checked.
+            let pointer_wraps = options.wrap_tape || options.unchecked;
+            let scan_amount = match body.as_slice() {
+                [Instruction::MoveRight] if !pointer_wraps => Some(1),
+                [Instruction::MoveLeft] if !pointer_wraps => Some(-1),
+                _ => None
+            };
+
+            if options.optimise && is_clear_loop {
+                optimised.ops.push(Op::Clear);
+                optimised.indexes.push(code_index);
+
+                code_index += 3;
+            }
+            else if let (true, Some(amount)) = (options.optimise, scan_amount) {
+                optimised.ops.push(Op::Scan(amount));
+                optimised.indexes.push(code_index);
+
+                code_index += 3;
+            }
+            else {
+                let start = optimised.ops.len();
+
+                // Filled in once the end of the loop is reached.
+                optimised.ops.push(Op::JumpIfZero(0));
+                optimised.indexes.push(code_index);
+
+                code_index = flatten(body, options, optimised, code_index + 1);
+
+                optimised.ops[start] = Op::JumpIfZero(optimised.ops.len());
+                optimised.ops.push(Op::JumpIfNotZero(start));
+                optimised.indexes.push(code_index);
+
+                code_index += 1;
+            }
+
+            i += 1;
+            continue;
+        }
+
+        let mut count = 1;
+
+        if options.optimise && matches!(current, Instruction::Increment | Instruction::Decrement | Instruction::MoveRight | Instruction::MoveLeft) {
+            while instructions.get(i + count) == Some(current) {
+                count += 1;
+            }
+        }
+
+        // Outside of strict mode, any other characters are comments.
+        if let Instruction::Unrecognised(ch) = current {
+            if !(options.strict || *ch == '#' && options.debug_char) {
+                i += 1;
+                code_index += 1;
+                continue;
+            }
+        }
+
+        let op = match current {
+            Instruction::Increment => Op::Add(count as isize),
+            Instruction::Decrement => Op::Add(-(count as isize)),
+            Instruction::MoveRight => Op::Move(count as isize),
+            Instruction::MoveLeft => Op::Move(-(count as isize)),
+            Instruction::Output => Op::Output,
+            Instruction::Input => Op::Input,
+            Instruction::Unrecognised('#') if options.debug_char => Op::Debug,
+            Instruction::Unrecognised(ch) => Op::Unrecognised(*ch),
+
+            // Handled above.
+            Instruction::Loop(_) => unreachable!()
+        };
+
+        optimised.ops.push(op);
+        optimised.indexes.push(code_index);
+
+        i += count;
+        code_index += count;
+    }
+
+    code_index
+}
@@ -0,0 +1,101 @@
+use crate::error::BrainfuckError;
+use crate::sanitise::SanitisedCode;
+
+// A single instruction, with while loops holding the
+// instructions inside of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    MoveRight,
+    MoveLeft,
+    Increment,
+    Decrement,
+    Output,
+    Input,
+    Loop(Vec<Instruction>),
+
+    // A character that isn't an instruction, which errors when it's run.
+    Unrecognised(char)
+}
+
+// Turn the sanitised code into a tree of instructions, one
+// level for every while loop. Sanitising already matches the brackets
+// up, but the code can be put together by hand, so they're checked again.
+pub fn parse_code(code: &SanitisedCode) -> Result<Vec<Instruction>, BrainfuckError> {
+    let mut instructions: Vec<Instruction> = Vec::new();
+
+    // The index of the "[" and the instructions so far of every loop that's still open.
+    let mut open_loops: Vec<(usize, Vec<Instruction>)> = Vec::new();
+
+    for (i, c) in code.code.iter().enumerate() {
+        let instruction = match c {
+            '>' => Instruction::MoveRight,
+            '<' => Instruction::MoveLeft,
+            '+' => Instruction::Increment,
+            '-' => Instruction::Decrement,
+            '.' => Instruction::Output,
+            ',' => Instruction::Input,
+
+            '[' => {
+                open_loops.push((i, Vec::new()));
+                continue;
+            }
+
+            ']' => match open_loops.pop() {
+                Some((_, body)) => Instruction::Loop(body),
+                None => return Err(BrainfuckError::TrailingLoop { pos: code.positions[i] })
+            },
+
+            _ => Instruction::Unrecognised(*c)
+        };
+
+        match open_loops.last_mut() {
+            Some((_, body)) => body.push(instruction),
+            None => instructions.push(instruction)
+        }
+    }
+
+    // The outermost loop that never got closed.
+    if let Some((start, _)) = open_loops.first() {
+        return Err(BrainfuckError::UnterminatedLoop { pos: code.positions[*start] });
+    }
+
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Position;
+
+    fn hand_built(code: &str) -> SanitisedCode {
+        SanitisedCode {
+            code: code.chars().collect(),
+            positions: (0..code.len()).map(|i| Position { index: i, line: 1, column: i + 1 }).collect(),
+            jump_table: (0..code.len()).collect()
+        }
+    }
+
+    #[test]
+    fn nested_loops() {
+        let instructions = parse_code(&hand_built("+[>[-]]")).unwrap();
+
+        assert_eq!(instructions, [
+            Instruction::Increment,
+            Instruction::Loop(vec![Instruction::MoveRight, Instruction::Loop(vec![Instruction::Decrement])])
+        ]);
+    }
+
+    #[test]
+    fn unmatched_open_bracket() {
+        let error = parse_code(&hand_built("+[[]")).unwrap_err();
+
+        assert!(matches!(error, BrainfuckError::UnterminatedLoop { pos: Position { column: 2, .. } }));
+    }
+
+    #[test]
+    fn unmatched_close_bracket() {
+        let error = parse_code(&hand_built("[]]")).unwrap_err();
+
+        assert!(matches!(error, BrainfuckError::TrailingLoop { pos: Position { column: 3, .. } }));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::BrainfuckError;
+use crate::parse::{parse_code, Instruction};
+use crate::sanitise::{sanitise_code, SanitisedCode};
+
+// Code that has already been sanitised and parsed, so it can be
+// run as many times as needed without being checked again.
+#[derive(Debug, Clone)]
+pub struct Program {
+    code: SanitisedCode,
+    instructions: Vec<Instruction>
+}
+
+impl Program {
+    pub fn sanitised_code(&self) -> &SanitisedCode {
+        &self.code
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+}
+
+impl FromStr for Program {
+    type Err = BrainfuckError;
+
+    fn from_str(code: &str) -> Result<Program, BrainfuckError> {
+        let code = sanitise_code(code)?;
+        let instructions = parse_code(&code)?;
+
+        Ok(Program { code, instructions })
+    }
+}
+
+// Only the instructions, without any comments, whitespace or other
+// characters, which parses back into an equal program.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_instructions(f, &self.instructions)
+    }
+}
+
+fn write_instructions(f: &mut fmt::Formatter<'_>, instructions: &[Instruction]) -> fmt::Result {
+    for instruction in instructions {
+        match instruction {
+            Instruction::MoveRight => write!(f, ">")?,
+            Instruction::MoveLeft => write!(f, "<")?,
+            Instruction::Increment => write!(f, "+")?,
+            Instruction::Decrement => write!(f, "-")?,
+            Instruction::Output => write!(f, ".")?,
+            Instruction::Input => write!(f, ",")?,
+
+            Instruction::Loop(body) => {
+                write!(f, "[")?;
+                write_instructions(f, body)?;
+                write!(f, "]")?;
+            }
+
+            Instruction::Unrecognised(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+// Programs are equal when they have the same instructions, wherever
+// they were in the code and whatever comments were around them.
+impl PartialEq for Program {
+    fn eq(&self, other: &Program) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl Eq for Program {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_only_has_the_instructions() {
+        let program: Program = "add one: + then loop [ -> + < ] // done".parse().unwrap();
+
+        assert_eq!(program.to_string(), "+[->+<]");
+    }
+
+    #[test]
+    fn display_parses_back_into_the_same_program() {
+        let program: Program = "++ /* comment */ [>+\n<-] .,".parse().unwrap();
+        let reparsed: Program = program.to_string().parse().unwrap();
+
+        assert_eq!(program, reparsed);
+        assert_eq!(reparsed.to_string(), program.to_string());
+    }
+}
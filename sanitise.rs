@@ -0,0 +1,204 @@
+use crate::error::{BrainfuckError, Position};
+
+// Code with the comments and whitespace stripped out, along with where
+// each of the remaining characters was in the original code, and where
+// each while loop character jumps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitisedCode {
+    pub code: Vec<char>,
+    pub positions: Vec<Position>,
+    pub jump_table: Vec<usize>
+}
+
+// A piece of the original code, as it gets split up while sanitising.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    // Anything that isn't whitespace or part of a comment, which
+    // is what ends up in the sanitised code.
+    Char(char, Position),
+
+    // A whole comment, including the "//" or "/*" and "*/".
+    Comment(String),
+
+    // A line break outside of a comment.
+    Newline,
+
+    // A space or tab outside of a comment, which only matters for keeping
+    // the words of plain text comments apart when formatting.
+    Space
+}
+
+pub fn sanitise_code(code: &str) -> Result<SanitisedCode, BrainfuckError> {
+    let mut sanitised = SanitisedCode {
+        code: Vec::new(),
+        positions: Vec::new(),
+        jump_table: Vec::new()
+    };
+
+    for token in tokenise_code(code)? {
+        if let Token::Char(c, position) = token {
+            sanitised.code.push(c);
+            sanitised.positions.push(position);
+        }
+    }
+
+    sanitised.jump_table = build_jump_table(&sanitised)?;
+
+    Ok(sanitised)
+}
+
+// Split the code up into the characters that get run, the comments,
+// the line breaks and the spaces between them.
+pub(crate) fn tokenise_code(code: &str) -> Result<Vec<Token>, BrainfuckError> {
+    let chars: Vec<char> = code.chars().collect();
+
+    let mut tokens = Vec::new();
+    let mut char_count = 0;
+
+    let mut line = 1;
+    let mut column = 1;
+    let mut i = 0;
+
+    // A "#!" line at the very top lets brainfuck files be run as scripts,
+    // so it's treated like a comment.
+    if code.starts_with("#!") {
+        while i < chars.len() && chars[i] != '\n' {
+            i += 1;
+        }
+
+        tokens.push(Token::Comment(chars[..i].iter().collect()));
+    }
+
+    while i < chars.len() {
+        let current = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let position = Position { index: char_count, line, column };
+
+        match (current, next) {
+            // Line comments run until the end of the line.
+            ('/', Some('/')) => {
+                let start = i;
+
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                    column += 1;
+                }
+
+                tokens.push(Token::Comment(chars[start..i].iter().collect()));
+
+                continue;
+            }
+
+            // Multi-line comments run until the closing "*/".
+            ('/', Some('*')) => {
+                let start = i;
+
+                i += 2;
+                column += 2;
+
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('*'), Some('/')) => break,
+
+                        (Some('\n'), _) => {
+                            line += 1;
+                            column = 1;
+                        }
+
+                        (Some(_), _) => column += 1,
+
+                        (None, _) => return Err(BrainfuckError::UnterminatedComment { pos: position })
+                    }
+
+                    i += 1;
+                }
+
+                i += 2;
+                column += 2;
+
+                tokens.push(Token::Comment(chars[start..i].iter().collect()));
+
+                continue;
+            }
+
+            ('*', Some('/')) => return Err(BrainfuckError::StrayComment { pos: position }),
+
+            ('\n', _) => {
+                line += 1;
+                column = 0;
+
+                tokens.push(Token::Newline);
+            }
+
+            (' ' | '\t', _) => tokens.push(Token::Space),
+
+            ('\r', _) => {}
+
+            _ => {
+                tokens.push(Token::Char(current, position));
+                char_count += 1;
+            }
+        }
+
+        i += 1;
+        column += 1;
+    }
+
+    Ok(tokens)
+}
+
+fn build_jump_table(code: &SanitisedCode) -> Result<Vec<usize>, BrainfuckError> {
+    // Maps each "[" to its matching "]" and vice versa, so the
+    // while loops can jump in a single step. Every other index
+    // is left pointing at itself.
+    //
+    // Matching the brackets up also finds the exact one that is
+    // unmatched, if there is one.
+    let mut jump_table: Vec<usize> = (0..code.code.len()).collect();
+    let mut while_loop_start_indexes: Vec<usize> = Vec::new();
+
+    for (i, c) in code.code.iter().enumerate() {
+        match c {
+            '[' => while_loop_start_indexes.push(i),
+
+            ']' => {
+                if let Some(start) = while_loop_start_indexes.pop() {
+                    jump_table[start] = i;
+                    jump_table[i] = start;
+                }
+                else {
+                    return Err(BrainfuckError::TrailingLoop { pos: code.positions[i] });
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    // The outermost loop that never got closed.
+    if let Some(start) = while_loop_start_indexes.first() {
+        return Err(BrainfuckError::UnterminatedLoop { pos: code.positions[*start] });
+    }
+
+    Ok(jump_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unmatched_open_bracket() {
+        let error = sanitise_code("[[]").unwrap_err();
+
+        assert!(matches!(error, BrainfuckError::UnterminatedLoop { pos: Position { line: 1, column: 1, .. } }));
+    }
+
+    #[test]
+    fn unmatched_close_bracket() {
+        let error = sanitise_code("[]]").unwrap_err();
+
+        assert!(matches!(error, BrainfuckError::TrailingLoop { pos: Position { line: 1, column: 3, .. } }));
+    }
+}
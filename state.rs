@@ -0,0 +1,105 @@
+// Everything needed to pick a run back up from where it was left,
+// apart from the code itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+    pub memory: Vec<u32>,
+    pub ptr: usize,
+    pub furthest_ptr: usize,
+
+    // The index in the memory of the block the pointer started on, which is
+    // more than 0 once memory blocks have been added to the left of it.
+    pub origin: usize,
+
+    // The index of the next instruction to run in the sanitised code.
+    pub code_index: usize,
+
+    // How many bytes of input had been read, which get skipped when resuming.
+    pub input_bytes: usize
+}
+
+// Written at the start of every state file, followed by the version.
+const STATE_MAGIC: &[u8; 4] = b"BFST";
+const STATE_VERSION: u8 = 2;
+
+impl State {
+    // Encode the state as bytes: the magic and version, then the pointer, furthest
+    // pointer, origin, code index, input read and memory size as little-endian 64-bit
+    // numbers, then each memory block as a little-endian 32-bit number.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(53 + self.memory.len() * 4);
+
+        bytes.extend_from_slice(STATE_MAGIC);
+        bytes.push(STATE_VERSION);
+
+        for number in [self.ptr, self.furthest_ptr, self.origin, self.code_index, self.input_bytes, self.memory.len()] {
+            bytes.extend_from_slice(&(number as u64).to_le_bytes());
+        }
+
+        for value in &self.memory {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    // Decode a state written by `to_bytes`, or None if the bytes aren't one.
+    pub fn from_bytes(bytes: &[u8]) -> Option<State> {
+        let rest = bytes.strip_prefix(STATE_MAGIC)?;
+        let (version, rest) = rest.split_first()?;
+
+        if *version != STATE_VERSION {
+            return None;
+        }
+
+        let mut numbers = [0; 6];
+        let mut rest = rest;
+
+        for number in &mut numbers {
+            let (number_bytes, remaining) = rest.split_first_chunk::<8>()?;
+
+            *number = usize::try_from(u64::from_le_bytes(*number_bytes)).ok()?;
+            rest = remaining;
+        }
+
+        let [ptr, furthest_ptr, origin, code_index, input_bytes, memory_size] = numbers;
+
+        if rest.len() != memory_size.checked_mul(4)? {
+            return None;
+        }
+
+        let memory = rest
+            .chunks_exact(4)
+            .map(|value_bytes| u32::from_le_bytes([value_bytes[0], value_bytes[1], value_bytes[2], value_bytes[3]]))
+            .collect();
+
+        Some(State { memory, ptr, furthest_ptr, origin, code_index, input_bytes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_round_trip() {
+        let state = State {
+            memory: vec![7, 0, u32::MAX, 0x110000, 1],
+            ptr: 3,
+            furthest_ptr: 4,
+            origin: 2,
+            code_index: 17,
+            input_bytes: 5
+        };
+
+        assert_eq!(State::from_bytes(&state.to_bytes()), Some(state));
+    }
+
+    #[test]
+    fn cut_short_or_old_states_are_rejected() {
+        let state = State { memory: vec![1, 2], ptr: 0, furthest_ptr: 1, origin: 0, code_index: 0, input_bytes: 0 };
+        let bytes = state.to_bytes();
+
+        assert_eq!(State::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(State::from_bytes(b"BFST\x01"), None);
+    }
+}